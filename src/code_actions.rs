@@ -0,0 +1,437 @@
+use std::ops;
+
+use tower_lsp::lsp_types::{Range, TextEdit};
+
+use crate::dom_tree::DomTree;
+use crate::parsers::stylesheet::{parse_stylesheet, Node};
+use crate::parsers::tokenizer::{tokenize, TokenKind};
+use crate::specificity::split_selector_list;
+use crate::types::offset_to_position;
+
+/// Diagnostic code attached to undefined-variable diagnostics so code actions
+/// can recognize them.
+pub const UNDEFINED_VARIABLE_CODE: &str = "undefined-variable";
+
+/// Diagnostic code for undefined variables whose `var()` has a fallback, which
+/// keeps the declaration valid.
+pub const UNDEFINED_VARIABLE_FALLBACK_CODE: &str = "undefined-variable-with-fallback";
+
+/// Diagnostic code for fallbacks of undefined variables that depend on
+/// another undefined variable without a usable fallback.
+pub const UNDEFINED_FALLBACK_CODE: &str = "undefined-fallback-variable";
+
+/// Diagnostic code for values that do not match their `@property` syntax.
+pub const REGISTERED_SYNTAX_CODE: &str = "registered-syntax-mismatch";
+
+/// Diagnostic code for literals that equal an existing variable's value.
+pub const HARDCODED_VALUE_CODE: &str = "hardcoded-token-value";
+
+/// Diagnostic code for definitions that are never referenced.
+pub const UNUSED_VARIABLE_CODE: &str = "unused-variable";
+
+/// Diagnostic code for definitions that take part in a `var()` cycle.
+pub const CIRCULAR_REFERENCE_CODE: &str = "circular-reference";
+
+/// `data.action` of the workspace-wide extract action resolved in `codeAction/resolve`.
+pub const EXTRACT_ALL_ACTION: &str = "extractAll";
+
+/// Value inserted for a new variable when its usage has no fallback.
+/// `initial` is the guaranteed-invalid value, so behavior is unchanged until
+/// the placeholder is replaced.
+pub const PLACEHOLDER_VALUE: &str = "initial";
+
+/// Edit inserting `declaration` (e.g. `--gap: 1rem;`) into the top-level
+/// `:root` rule nearest to `near`, or into a new `:root` rule at the top of
+/// the stylesheet, after any leading `@charset`, `@import`, `@layer` and
+/// `@namespace` statements. Rules whose selector list includes `:root`
+/// (e.g. `:root, :host`) count; rules nested in `@media`, `@layer` or other
+/// blocks do not, since their declarations are conditional or layered.
+/// HTML-like documents use their `<style>` blocks and return `None` when
+/// they have none.
+pub fn root_insertion_edit(
+    text: &str,
+    is_html: bool,
+    near: usize,
+    declaration: &str,
+) -> Option<TextEdit> {
+    let segments = css_segments(text, is_html, false);
+
+    let mut best: Option<(usize, usize, String)> = None;
+    for (base, css) in &segments {
+        let stylesheet = parse_stylesheet(css);
+        for node in &stylesheet.nodes {
+            let rule = match node {
+                Node::StyleRule(rule)
+                    if split_selector_list(&rule.selector)
+                        .iter()
+                        .any(|selector| selector.trim() == ":root") =>
+                {
+                    rule
+                }
+                _ => continue,
+            };
+            let start = base + rule.span.start;
+            let end = base + rule.span.end;
+            let distance = if near < start {
+                start - near
+            } else {
+                near.saturating_sub(end)
+            };
+            if best.as_ref().is_some_and(|(d, _, _)| *d <= distance) {
+                continue;
+            }
+
+            let close = if css[..rule.span.end].ends_with('}') {
+                rule.span.end - 1
+            } else {
+                rule.span.end
+            };
+            let content = css[rule.span.start..close].trim_end();
+            let insert_at = rule.span.start + content.len();
+            let separator = if content.ends_with('{') || content.ends_with(';') {
+                ""
+            } else {
+                ";"
+            };
+            let indent = match rule.children.last() {
+                Some(Node::Declaration(last)) => line_indent(css, last.span.start),
+                _ => format!("{}  ", line_indent(css, rule.span.start)),
+            };
+            let new_text = format!("{}\n{}{}", separator, indent, declaration);
+            best = Some((distance, base + insert_at, new_text));
+        }
+    }
+
+    let (offset, new_text) = match best {
+        Some((_, offset, new_text)) => (offset, new_text),
+        None => {
+            let (base, css) = segments.first()?;
+            let rule = format!(":root {{\n  {}\n}}", declaration);
+            match leading_statements_end(css) {
+                Some(end) => (base + end, format!("\n\n{}", rule)),
+                None => {
+                    let leading = if is_html { "\n" } else { "" };
+                    (*base, format!("{}{}\n\n", leading, rule))
+                }
+            }
+        }
+    };
+    let position = offset_to_position(text, offset);
+    Some(TextEdit {
+        range: Range::new(position, position),
+        new_text,
+    })
+}
+
+/// End of the `@charset`, `@import`, `@layer` and `@namespace` statements
+/// at the start of a stylesheet, which must precede its style rules.
+fn leading_statements_end(css: &str) -> Option<usize> {
+    parse_stylesheet(css)
+        .nodes
+        .iter()
+        .map_while(|node| match node {
+            Node::AtRule(at_rule)
+                if at_rule.children.is_none()
+                    && matches!(
+                        at_rule.name.as_str(),
+                        "charset" | "import" | "layer" | "namespace"
+                    ) =>
+            {
+                Some(at_rule.span.end)
+            }
+            _ => None,
+        })
+        .last()
+}
+
+/// A declaration value in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclarationValue {
+    /// Property name (e.g., "color" or "--gap")
+    pub property: String,
+
+    /// Byte span of the value in the document, excluding `!important`
+    pub span: ops::Range<usize>,
+}
+
+/// Every declaration value in a stylesheet, or in the `<style>` blocks and
+/// `style` attributes of an HTML-like document. Descriptors of `@property`
+/// rules are skipped since they cannot contain `var()`.
+pub fn declaration_values(text: &str, is_html: bool) -> Vec<DeclarationValue> {
+    let mut values = Vec::new();
+    for (base, css) in css_segments(text, is_html, true) {
+        let stylesheet = parse_stylesheet(&css);
+        collect_declaration_values(&stylesheet.nodes, base, &mut values);
+    }
+    values
+}
+
+/// Whether `span` of `value` starts and ends on token boundaries with
+/// balanced parentheses, so it can stand on its own as a value (e.g. not
+/// `82f` inside `#3b82f6`, nor `rgb(0 0` inside `rgb(0 0 0)`).
+pub fn covers_whole_tokens(value: &str, span: ops::Range<usize>) -> bool {
+    let mut starts_on_boundary = false;
+    let mut ends_on_boundary = false;
+    let mut depth = 0i32;
+    for token in tokenize(value) {
+        if token.end <= span.start || token.start >= span.end {
+            continue;
+        }
+        if token.start < span.start || token.end > span.end {
+            return false;
+        }
+        starts_on_boundary |= token.start == span.start;
+        ends_on_boundary |= token.end == span.end;
+        match token.kind {
+            TokenKind::Function | TokenKind::LeftParen => depth += 1,
+            TokenKind::RightParen => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            return false;
+        }
+    }
+    starts_on_boundary && ends_on_boundary && depth == 0
+}
+
+fn collect_declaration_values(nodes: &[Node], base: usize, values: &mut Vec<DeclarationValue>) {
+    for node in nodes {
+        match node {
+            Node::Declaration(declaration) => values.push(DeclarationValue {
+                property: declaration.name.clone(),
+                span: base + declaration.value_span.start..base + declaration.value_span.end,
+            }),
+            Node::StyleRule(rule) => collect_declaration_values(&rule.children, base, values),
+            Node::AtRule(at_rule) if at_rule.name == "property" => {}
+            Node::AtRule(at_rule) => {
+                if let Some(children) = &at_rule.children {
+                    collect_declaration_values(children, base, values);
+                }
+            }
+        }
+    }
+}
+
+/// Byte spans of every occurrence of `literal` inside declaration values,
+/// compared token by token so `1rem` does not match inside `11rem`.
+pub fn find_literal_occurrences(
+    text: &str,
+    is_html: bool,
+    literal: &str,
+) -> Vec<ops::Range<usize>> {
+    let needle: Vec<&str> = tokenize(literal)
+        .iter()
+        .filter(|t| !t.is_whitespace())
+        .map(|t| t.text(literal))
+        .collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let mut occurrences = Vec::new();
+    for value in declaration_values(text, is_html) {
+        let source = &text[value.span.clone()];
+        let tokens: Vec<_> = tokenize(source)
+            .into_iter()
+            .filter(|t| !t.is_whitespace())
+            .collect();
+        let mut i = 0;
+        while i + needle.len() <= tokens.len() {
+            let matched = needle
+                .iter()
+                .enumerate()
+                .all(|(k, expected)| tokens[i + k].text(source) == *expected);
+            if matched {
+                let last = tokens[i + needle.len() - 1];
+                occurrences.push(value.span.start + tokens[i].start..value.span.start + last.end);
+                i += needle.len();
+            } else {
+                i += 1;
+            }
+        }
+    }
+    occurrences
+}
+
+/// Name for a variable extracted from a `property` value, made unique with a
+/// numeric suffix (e.g. "--color-2") when `is_taken` reports a collision.
+pub fn suggest_variable_name(property: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    let base = if property.starts_with("--") {
+        format!("{}-value", property)
+    } else {
+        format!("--{}", property.to_ascii_lowercase())
+    };
+    if !is_taken(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|candidate| !is_taken(candidate))
+        .unwrap()
+}
+
+/// Byte span removing the declaration at `start..end` together with its
+/// trailing `;`, and its whole line when nothing else is on it.
+pub fn declaration_removal_range(text: &str, start: usize, end: usize) -> ops::Range<usize> {
+    let mut end = end;
+    let rest = &text[end..];
+    let trimmed = rest.trim_start_matches([' ', '\t']);
+    if let Some(after) = trimmed.strip_prefix(';') {
+        end = text.len() - after.len();
+    }
+
+    let line_start = text[..start].rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+    let after = &text[end..];
+    let line_end = after
+        .find('\n')
+        .map(|idx| end + idx + 1)
+        .unwrap_or(text.len());
+    let alone = text[line_start..start].trim().is_empty() && text[end..line_end].trim().is_empty();
+    if alone {
+        line_start..line_end
+    } else {
+        start..end
+    }
+}
+
+/// CSS regions of a document with their byte offsets: the whole text for
+/// stylesheets, or the `<style>` blocks (and optionally `style` attributes)
+/// of HTML-like documents.
+fn css_segments(text: &str, is_html: bool, include_inline: bool) -> Vec<(usize, String)> {
+    if !is_html {
+        return vec![(0, text.to_string())];
+    }
+    let parsed = DomTree::parse(text);
+    let mut segments: Vec<(usize, String)> = parsed
+        .style_blocks
+        .into_iter()
+        .map(|block| (block.content_start, block.content))
+        .collect();
+    if include_inline {
+        segments.extend(
+            parsed
+                .inline_styles
+                .into_iter()
+                .map(|inline| (inline.value_start, inline.value)),
+        );
+    }
+    segments
+}
+
+/// Leading whitespace of the line containing `offset`.
+fn line_indent(text: &str, offset: usize) -> String {
+    let line_start = text[..offset].rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+    text[line_start..]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::Position;
+
+    #[test]
+    fn selections_must_cover_whole_tokens() {
+        let value = "1px solid #3b82f6";
+        let span = |needle: &str| {
+            let start = value.find(needle).unwrap();
+            start..start + needle.len()
+        };
+        assert!(covers_whole_tokens(value, span("#3b82f6")));
+        assert!(covers_whole_tokens(value, span("solid #3b82f6")));
+        assert!(!covers_whole_tokens(value, span("82f")));
+        assert!(!covers_whole_tokens(value, span("1p")));
+
+        let value = "rgb(0 0 0) 2px";
+        assert!(covers_whole_tokens(value, 0..10));
+        assert!(!covers_whole_tokens(value, 0..7));
+        assert!(!covers_whole_tokens(value, 4..10));
+    }
+
+    #[test]
+    fn inserts_into_nearest_root_rule() {
+        let text = ":root {\n    --a: 1px;\n}\n.x { color: red; }\n:root {\n    --b: 2px\n}\n";
+        let edit = root_insertion_edit(text, false, text.len(), "--c: 3px;").unwrap();
+        assert_eq!(edit.range.start, Position::new(5, 12));
+        assert_eq!(edit.new_text, ";\n    --c: 3px;");
+
+        let edit = root_insertion_edit(text, false, 0, "--c: 3px;").unwrap();
+        assert_eq!(edit.range.start, Position::new(1, 13));
+        assert_eq!(edit.new_text, "\n    --c: 3px;");
+    }
+
+    #[test]
+    fn creates_root_rule_when_missing() {
+        let edit = root_insertion_edit(".x { color: red; }", false, 5, "--c: 3px;").unwrap();
+        assert_eq!(edit.range.start, Position::new(0, 0));
+        assert_eq!(edit.new_text, ":root {\n  --c: 3px;\n}\n\n");
+
+        let html = "<div></div>";
+        assert!(root_insertion_edit(html, true, 0, "--c: 3px;").is_none());
+
+        let html = "<style>:root { --a: 1px; }</style>";
+        let edit = root_insertion_edit(html, true, 0, "--c: 3px;").unwrap();
+        assert_eq!(edit.range.start, Position::new(0, 24));
+    }
+
+    #[test]
+    fn creates_root_rule_after_leading_statements() {
+        let text = "@charset \"utf-8\";\n@import url(\"base.css\");\n.x { color: red; }";
+        let edit = root_insertion_edit(text, false, 0, "--c: 3px;").unwrap();
+        assert_eq!(edit.range.start, Position::new(1, 24));
+        assert_eq!(edit.new_text, "\n\n:root {\n  --c: 3px;\n}");
+
+        let text = "@layer base, theme;\n@media print { :root { --a: 1px; } }";
+        let edit = root_insertion_edit(text, false, 0, "--c: 3px;").unwrap();
+        assert_eq!(edit.range.start, Position::new(0, 19));
+    }
+
+    #[test]
+    fn inserts_into_root_selector_lists() {
+        let text = ".x { color: red; }\n:root, :host { --a: 1px; }";
+        let edit = root_insertion_edit(text, false, 0, "--c: 3px;").unwrap();
+        assert_eq!(edit.range.start, Position::new(1, 24));
+    }
+
+    #[test]
+    fn finds_literal_occurrences_in_values_only() {
+        let text = "/* #3b82f6 */\n.a { color: #3b82f6; border: 1px solid #3b82f6 !important; }\n\
+                    .b { margin: 11rem; padding: calc(1rem + 2px); }";
+        let occurrences = find_literal_occurrences(text, false, "#3b82f6");
+        assert_eq!(occurrences.len(), 2);
+        assert!(occurrences
+            .iter()
+            .all(|range| &text[range.clone()] == "#3b82f6"));
+
+        assert_eq!(find_literal_occurrences(text, false, "1rem").len(), 1);
+        let calc = find_literal_occurrences(text, false, "calc(1rem + 2px)");
+        assert_eq!(&text[calc[0].clone()], "calc(1rem + 2px)");
+
+        let html = "<style>.a { color: red; }</style><p style=\"color: red\"></p>";
+        assert_eq!(find_literal_occurrences(html, true, "red").len(), 2);
+    }
+
+    #[test]
+    fn removes_declarations_with_their_line() {
+        let text = ":root {\n  --a: 1px;\n  --b: 2px;\n}";
+        let start = text.find("--a").unwrap();
+        let range = declaration_removal_range(text, start, start + "--a: 1px".len());
+        assert_eq!(&text[range], "  --a: 1px;\n");
+
+        let text = ":root { --a: 1px; --b: 2px; }";
+        let start = text.find("--a").unwrap();
+        let range = declaration_removal_range(text, start, start + "--a: 1px".len());
+        assert_eq!(&text[range], "--a: 1px;");
+    }
+
+    #[test]
+    fn suggests_unique_variable_names() {
+        assert_eq!(suggest_variable_name("Color", |_| false), "--color");
+        assert_eq!(
+            suggest_variable_name("--shadow", |name| name == "--shadow-value"),
+            "--shadow-value-2"
+        );
+    }
+}
@@ -88,10 +88,8 @@ fn parse_hex(hex: &str) -> Option<Color> {
 fn parse_rgb(value: &str) -> Option<Color> {
     let inner = if let Some(rest) = value.strip_prefix("rgba") {
         rest
-    } else if let Some(rest) = value.strip_prefix("rgb") {
-        rest
     } else {
-        return None;
+        value.strip_prefix("rgb")?
     };
 
     let inner = inner.trim_start().strip_prefix('(')?.strip_suffix(')')?;
@@ -0,0 +1,549 @@
+use crate::parsers::tokenizer::{tokenize, Token, TokenKind};
+
+/// Absolute length units and their size in px
+const LENGTH_UNITS: [(&str, f64); 7] = [
+    ("px", 1.0),
+    ("in", 96.0),
+    ("cm", 96.0 / 2.54),
+    ("mm", 96.0 / 25.4),
+    ("q", 96.0 / 101.6),
+    ("pt", 96.0 / 72.0),
+    ("pc", 16.0),
+];
+
+/// Angle units and their size in degrees
+const ANGLE_UNITS: [(&str, f64); 4] = [
+    ("deg", 1.0),
+    ("grad", 0.9),
+    ("rad", 180.0 / std::f64::consts::PI),
+    ("turn", 360.0),
+];
+
+/// Time units and their size in seconds
+const TIME_UNITS: [(&str, f64); 2] = [("s", 1.0), ("ms", 0.001)];
+
+/// Simplify every `calc()`, `min()`, `max()` and `clamp()` in a value,
+/// returning `None` when nothing could be simplified.
+///
+/// Terms with compatible units are combined (converting absolute lengths to
+/// px when they are mixed), percentages stay symbolic, and expressions with
+/// incompatible units are left partially simplified, e.g.
+/// `calc(1rem + 2px + 3px)` becomes `calc(1rem + 5px)`.
+pub fn evaluate_math(value: &str) -> Option<String> {
+    let tokens = tokenize(value);
+    let mut out = String::new();
+    let mut copied_to = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        if !is_math_function(value, &tokens[i]) {
+            i += 1;
+            continue;
+        }
+        let mut parser = Parser {
+            source: value,
+            tokens: &tokens,
+            pos: i,
+        };
+        let start = tokens[i].start;
+        let expr = parser.parse_function();
+        match expr {
+            Some(expr) => {
+                out.push_str(&value[copied_to..start]);
+                out.push_str(&expr.to_css(true));
+                copied_to = tokens[parser.pos - 1].end;
+                i = parser.pos;
+            }
+            None => i += 1,
+        }
+    }
+    if copied_to == 0 {
+        return None;
+    }
+    out.push_str(&value[copied_to..]);
+    (out != value).then_some(out)
+}
+
+fn is_math_function(source: &str, token: &Token) -> bool {
+    token.function_name(source).is_some_and(|name| {
+        ["calc", "min", "max", "clamp"].contains(&name.to_ascii_lowercase().as_str())
+    })
+}
+
+/// A math expression: a sum of terms per unit ("" for plain numbers), or
+/// text that could not be simplified further
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Terms(Vec<(f64, String)>),
+    Opaque(String),
+}
+
+impl Expr {
+    fn number(value: f64, unit: &str) -> Self {
+        Expr::Terms(vec![(value, unit.to_ascii_lowercase())])
+    }
+
+    /// The plain number if this is unitless
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Expr::Terms(terms) if terms.len() == 1 && terms[0].1.is_empty() => Some(terms[0].0),
+            _ => None,
+        }
+    }
+
+    /// The single (value, unit) if this has exactly one term
+    fn as_single(&self) -> Option<(f64, &str)> {
+        match self {
+            Expr::Terms(terms) if terms.len() == 1 => Some((terms[0].0, terms[0].1.as_str())),
+            _ => None,
+        }
+    }
+
+    fn add(self, other: Expr, sign: f64) -> Expr {
+        match (self, other) {
+            (Expr::Terms(mut terms), Expr::Terms(other)) => {
+                for (value, unit) in other {
+                    terms.push((value * sign, unit));
+                }
+                Expr::Terms(combine(terms))
+            }
+            (a, b) => {
+                // Subtracting a sum must keep its terms grouped.
+                let rhs = if sign < 0.0 && b.is_sum() {
+                    format!("({})", b.to_css(false))
+                } else {
+                    b.to_css(false)
+                };
+                let op = if sign < 0.0 { "-" } else { "+" };
+                Expr::Opaque(format!("{} {} {}", a.to_css(false), op, rhs))
+            }
+        }
+    }
+
+    fn scale(self, factor: f64) -> Expr {
+        match self {
+            Expr::Terms(terms) => Expr::Terms(
+                terms
+                    .into_iter()
+                    .map(|(value, unit)| (value * factor, unit))
+                    .collect(),
+            ),
+            opaque => Expr::Opaque(format!(
+                "{} * {}",
+                opaque.to_css_operand(),
+                format_number(factor, "")
+            )),
+        }
+    }
+
+    /// Whether this serializes as a sum or difference of several terms
+    fn is_sum(&self) -> bool {
+        match self {
+            Expr::Terms(terms) => terms.len() > 1,
+            Expr::Opaque(text) => !is_call(text) && has_top_level_sum(text),
+        }
+    }
+
+    fn multiply(self, other: Expr) -> Expr {
+        if let Some(factor) = other.as_number() {
+            return self.scale(factor);
+        }
+        if let Some(factor) = self.as_number() {
+            return other.scale(factor);
+        }
+        Expr::Opaque(format!(
+            "{} * {}",
+            self.to_css_operand(),
+            other.to_css_operand()
+        ))
+    }
+
+    fn divide(self, other: Expr) -> Expr {
+        match other.as_number() {
+            Some(divisor) if divisor != 0.0 && matches!(self, Expr::Terms(_)) => {
+                self.scale(1.0 / divisor)
+            }
+            _ => Expr::Opaque(format!(
+                "{} / {}",
+                self.to_css_operand(),
+                other.to_css_operand()
+            )),
+        }
+    }
+
+    /// Serialize; `top_level` wraps sums and products in `calc()`.
+    fn to_css(&self, top_level: bool) -> String {
+        let inner = match self {
+            Expr::Terms(terms) if terms.is_empty() => return "0".to_string(),
+            Expr::Terms(terms) => {
+                let mut text = format_number(terms[0].0, &terms[0].1);
+                if terms.len() == 1 {
+                    return text;
+                }
+                for (value, unit) in &terms[1..] {
+                    if *value < 0.0 {
+                        text.push_str(&format!(" - {}", format_number(-value, unit)));
+                    } else {
+                        text.push_str(&format!(" + {}", format_number(*value, unit)));
+                    }
+                }
+                text
+            }
+            // A lone min()/max()/clamp() needs no calc() around it.
+            Expr::Opaque(text) if is_call(text) => return text.clone(),
+            Expr::Opaque(text) => text.clone(),
+        };
+        if top_level {
+            format!("calc({})", inner)
+        } else {
+            inner
+        }
+    }
+
+    /// Serialize as an operand of `*` or `/`, parenthesizing sums.
+    fn to_css_operand(&self) -> String {
+        match self {
+            Expr::Terms(terms) if terms.len() <= 1 => self.to_css(false),
+            Expr::Opaque(text) if is_call(text) => text.clone(),
+            _ => format!("({})", self.to_css(false)),
+        }
+    }
+}
+
+/// Merge terms with compatible units, keeping the first-seen unit order.
+fn combine(terms: Vec<(f64, String)>) -> Vec<(f64, String)> {
+    let mut combined: Vec<(f64, String)> = Vec::new();
+    for (value, unit) in terms {
+        if let Some(existing) = combined.iter_mut().find(|(_, u)| *u == unit) {
+            existing.0 += value;
+            continue;
+        }
+        let converted = [&LENGTH_UNITS[..], &ANGLE_UNITS[..], &TIME_UNITS[..]]
+            .iter()
+            .find_map(|group| {
+                let factor = group.iter().find(|(u, _)| *u == unit)?.1;
+                let (canonical, _) = group[0];
+                let existing = combined
+                    .iter_mut()
+                    .find(|(_, u)| group.iter().any(|(g, _)| g == u))?;
+                // Convert both sides to the canonical unit of the group.
+                let existing_factor = group.iter().find(|(u, _)| *u == existing.1)?.1;
+                existing.0 = existing.0 * existing_factor + value * factor;
+                existing.1 = canonical.to_string();
+                Some(())
+            });
+        if converted.is_none() {
+            combined.push((value, unit));
+        }
+    }
+    combined
+}
+
+fn format_number(value: f64, unit: &str) -> String {
+    let rounded = (value * 10000.0).round() / 10000.0;
+    let mut text = format!("{:.4}", rounded);
+    while text.ends_with('0') {
+        text.pop();
+    }
+    if text.ends_with('.') {
+        text.pop();
+    }
+    if text == "-0" {
+        text = "0".to_string();
+    }
+    format!("{}{}", text, unit)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.tokens.len() && self.tokens[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn peek_delim(&mut self) -> Option<char> {
+        let saved = self.pos;
+        self.skip_whitespace();
+        let delim = match self.peek()?.kind {
+            TokenKind::Delim(c) => Some(c),
+            _ => None,
+        };
+        self.pos = saved;
+        delim
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Option<()> {
+        self.skip_whitespace();
+        if self.peek()?.kind == kind {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    /// Parse a math function starting at its function token, consuming the
+    /// closing parenthesis.
+    fn parse_function(&mut self) -> Option<Expr> {
+        let name = self.tokens[self.pos]
+            .function_name(self.source)?
+            .to_ascii_lowercase();
+        self.pos += 1;
+
+        if name == "calc" {
+            let expr = self.parse_sum()?;
+            self.expect(TokenKind::RightParen)?;
+            return Some(expr);
+        }
+
+        let mut args = vec![self.parse_sum()?];
+        loop {
+            self.skip_whitespace();
+            match self.peek()?.kind {
+                TokenKind::Comma => {
+                    self.pos += 1;
+                    args.push(self.parse_sum()?);
+                }
+                TokenKind::RightParen => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => return None,
+            }
+        }
+        Some(evaluate_comparison(&name, args))
+    }
+
+    fn parse_sum(&mut self) -> Option<Expr> {
+        let mut expr = self.parse_product()?;
+        while let Some(op @ ('+' | '-')) = self.peek_delim() {
+            self.skip_whitespace();
+            self.pos += 1;
+            let rhs = self.parse_product()?;
+            expr = expr.add(rhs, if op == '-' { -1.0 } else { 1.0 });
+        }
+        Some(expr)
+    }
+
+    fn parse_product(&mut self) -> Option<Expr> {
+        let mut expr = self.parse_value()?;
+        while let Some(op @ ('*' | '/')) = self.peek_delim() {
+            self.skip_whitespace();
+            self.pos += 1;
+            let rhs = self.parse_value()?;
+            expr = if op == '*' {
+                expr.multiply(rhs)
+            } else {
+                expr.divide(rhs)
+            };
+        }
+        Some(expr)
+    }
+
+    fn parse_value(&mut self) -> Option<Expr> {
+        self.skip_whitespace();
+        let token = self.peek()?;
+        let text = token.text(self.source);
+        match token.kind {
+            TokenKind::Number => {
+                self.pos += 1;
+                Some(Expr::number(text.parse().ok()?, ""))
+            }
+            TokenKind::Percentage => {
+                self.pos += 1;
+                Some(Expr::number(text.strip_suffix('%')?.parse().ok()?, "%"))
+            }
+            TokenKind::Dimension => {
+                self.pos += 1;
+                let split = unit_start(text)?;
+                Some(Expr::number(text[..split].parse().ok()?, &text[split..]))
+            }
+            TokenKind::LeftParen => {
+                self.pos += 1;
+                let expr = self.parse_sum()?;
+                self.expect(TokenKind::RightParen)?;
+                Some(expr)
+            }
+            // A nested calc() is just parentheses.
+            TokenKind::Function if is_math_function(self.source, &token) => self.parse_function(),
+            _ => None,
+        }
+    }
+}
+
+/// Byte offset where a dimension's unit begins, skipping a number's exponent
+fn unit_start(dimension: &str) -> Option<usize> {
+    let bytes = dimension.as_bytes();
+    (1..bytes.len()).find(|&idx| {
+        let is_exponent = matches!(bytes[idx], b'e' | b'E')
+            && bytes
+                .get(idx + 1)
+                .is_some_and(|b| b.is_ascii_digit() || matches!(b, b'+' | b'-'));
+        bytes[idx].is_ascii_alphabetic() && !is_exponent
+    })
+}
+
+/// Whether `text` is a single function call such as `clamp(1rem, 2vw, 2rem)`
+fn is_call(text: &str) -> bool {
+    let Some(open) = text.find('(') else {
+        return false;
+    };
+    let mut depth = 0;
+    for (idx, c) in text.char_indices().skip(open) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return idx == text.len() - 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Whether `text` has a `+` or `-` operator outside parentheses
+fn has_top_level_sum(text: &str) -> bool {
+    let mut depth = 0i32;
+    let bytes = text.as_bytes();
+    for (idx, byte) in bytes.iter().enumerate() {
+        match byte {
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            b'+' | b'-' if depth == 0 => {
+                // Operators are surrounded by whitespace in calc().
+                let spaced = idx > 0
+                    && bytes[idx - 1] == b' '
+                    && bytes.get(idx + 1).is_some_and(|b| *b == b' ');
+                if spaced {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Evaluate `min()`, `max()` or `clamp()` when every argument reduces to a
+/// single term in one unit, otherwise keep the function with simplified
+/// arguments.
+fn evaluate_comparison(name: &str, args: Vec<Expr>) -> Expr {
+    let singles: Option<Vec<(f64, &str)>> = args.iter().map(|arg| arg.as_single()).collect();
+    if let Some(singles) = singles {
+        let unit = singles[0].1;
+        if singles.iter().all(|(_, u)| *u == unit) {
+            let values: Vec<f64> = singles.iter().map(|(v, _)| *v).collect();
+            let result = match (name, values.as_slice()) {
+                ("min", values) => values.iter().copied().reduce(f64::min),
+                ("max", values) => values.iter().copied().reduce(f64::max),
+                ("clamp", [low, value, high]) => Some(value.min(*high).max(*low)),
+                _ => None,
+            };
+            if let Some(result) = result {
+                return Expr::number(result, unit);
+            }
+        }
+    }
+    let args: Vec<String> = args.iter().map(|arg| arg.to_css(false)).collect();
+    Expr::Opaque(format!("{}({})", name, args.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluates_arithmetic_with_units() {
+        assert_eq!(evaluate_math("calc(0.25rem * 4)").as_deref(), Some("1rem"));
+        assert_eq!(
+            evaluate_math("calc((1px + 2px) * 2 / 3)").as_deref(),
+            Some("2px")
+        );
+        assert_eq!(evaluate_math("calc(1in + 4px)").as_deref(), Some("100px"));
+        assert_eq!(
+            evaluate_math("calc(0.5turn - 90deg)").as_deref(),
+            Some("90deg")
+        );
+        assert_eq!(evaluate_math("calc(2 * 3)").as_deref(), Some("6"));
+    }
+
+    #[test]
+    fn evaluates_comparison_functions() {
+        assert_eq!(evaluate_math("min(10px, 2px * 3)").as_deref(), Some("6px"));
+        assert_eq!(evaluate_math("max(1rem, 2rem)").as_deref(), Some("2rem"));
+        assert_eq!(
+            evaluate_math("clamp(1rem, calc(0.5rem * 5), 2rem)").as_deref(),
+            Some("2rem")
+        );
+        assert_eq!(
+            evaluate_math("clamp(1rem, calc(1vw * 2), 1.5rem + 0.5rem)").as_deref(),
+            Some("clamp(1rem, 2vw, 2rem)")
+        );
+        assert_eq!(evaluate_math("clamp(1rem, 2vw, 2rem)"), None);
+    }
+
+    #[test]
+    fn leaves_incompatible_units_partially_simplified() {
+        assert_eq!(
+            evaluate_math("calc(100% - 2 * 8px)").as_deref(),
+            Some("calc(100% - 16px)")
+        );
+        assert_eq!(
+            evaluate_math("calc(1rem + 2px + 3px)").as_deref(),
+            Some("calc(1rem + 5px)")
+        );
+        assert_eq!(
+            evaluate_math("1px solid calc(2px * 2)").as_deref(),
+            Some("1px solid 4px")
+        );
+        assert_eq!(evaluate_math("calc(var(--x) * 2)"), None);
+        assert_eq!(evaluate_math("10px"), None);
+    }
+
+    #[test]
+    fn keeps_factors_of_unreduced_operands() {
+        assert_eq!(evaluate_math("calc(min(1rem, 2vw) * 2)"), None);
+        assert_eq!(
+            evaluate_math("calc(2 * min(1rem, 2vw))").as_deref(),
+            Some("calc(min(1rem, 2vw) * 2)")
+        );
+        assert_eq!(evaluate_math("calc(clamp(1rem, 2vw, 3rem) / 2)"), None);
+        assert_eq!(
+            evaluate_math("calc(clamp(1rem, 2vw, 3rem) / (1 + 1))").as_deref(),
+            Some("calc(clamp(1rem, 2vw, 3rem) / 2)")
+        );
+        assert_eq!(
+            evaluate_math("calc(max(1rem, 2vw) * (1 + 0.5))").as_deref(),
+            Some("calc(max(1rem, 2vw) * 1.5)")
+        );
+    }
+
+    #[test]
+    fn groups_subtracted_sums() {
+        assert_eq!(evaluate_math("calc(min(1px, 2vw) - (1rem + 2px))"), None);
+        assert_eq!(
+            evaluate_math("calc(min(1px, 2vw) - (1rem + 1px + 1px))").as_deref(),
+            Some("calc(min(1px, 2vw) - (1rem + 2px))")
+        );
+        assert_eq!(
+            evaluate_math("calc(min(1px, 2vw) - (max(1rem, 2vw) - 2px))").as_deref(),
+            None
+        );
+        assert_eq!(
+            evaluate_math("calc(min(1px, 2vw) - 2 * 3px)").as_deref(),
+            Some("calc(min(1px, 2vw) - 6px)")
+        );
+    }
+}
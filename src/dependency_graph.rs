@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ops::Range;
+
+use tower_lsp::lsp_types::Location;
+
+use crate::parsers::tokenizer::{tokenize, TokenKind};
+use crate::types::CssVariable;
+
+/// Names of every `var()` referenced by a value, including references nested
+/// in fallbacks and math functions, in source order without duplicates.
+pub fn var_references(value: &str) -> Vec<String> {
+    let tokens = tokenize(value);
+    let mut names = Vec::new();
+    for (idx, token) in tokens.iter().enumerate() {
+        let is_var = token
+            .function_name(value)
+            .is_some_and(|name| name.eq_ignore_ascii_case("var"));
+        if !is_var {
+            continue;
+        }
+        let name = tokens[idx + 1..]
+            .iter()
+            .find(|t| !t.is_whitespace())
+            .filter(|t| t.kind == TokenKind::Ident && t.text(value).starts_with("--"))
+            .map(|t| t.text(value).to_string());
+        if let Some(name) = name {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// A `var()` call in a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VarCall<'a> {
+    /// Referenced custom property name
+    pub name: &'a str,
+
+    /// Trimmed text after the first top-level comma, if any
+    pub fallback: Option<&'a str>,
+
+    /// Byte span of the whole call, `var(` through `)`
+    pub span: Range<usize>,
+}
+
+/// The `var()` calls of a value in source order, including those inside math
+/// functions but not those nested in another call's fallback.
+pub fn var_calls(value: &str) -> Vec<VarCall<'_>> {
+    let tokens = tokenize(value);
+    let mut calls = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let is_var = tokens[i]
+            .function_name(value)
+            .is_some_and(|name| name.eq_ignore_ascii_case("var"));
+        if !is_var {
+            i += 1;
+            continue;
+        }
+
+        // Find the closing parenthesis and the first top-level comma.
+        let mut depth = 0usize;
+        let mut comma = None;
+        let mut close = None;
+        for (j, token) in tokens.iter().enumerate().skip(i) {
+            match token.kind {
+                TokenKind::Function | TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(j);
+                        break;
+                    }
+                }
+                TokenKind::Comma if depth == 1 && comma.is_none() => comma = Some(j),
+                _ => {}
+            }
+        }
+        let call_end = close.map(|j| tokens[j].end).unwrap_or(value.len());
+        let args_end = close.map(|j| tokens[j].start).unwrap_or(value.len());
+        let name_end = comma.map(|j| tokens[j].start).unwrap_or(args_end);
+        calls.push(VarCall {
+            name: value[tokens[i].end..name_end].trim(),
+            fallback: comma.map(|j| value[tokens[j].end..args_end].trim()),
+            span: tokens[i].start..call_end,
+        });
+        i = close.map(|j| j + 1).unwrap_or(tokens.len());
+    }
+    calls
+}
+
+/// Workspace-wide graph of `var()` references between custom properties
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// Variable name -> referenced name -> definitions making the reference
+    edges: HashMap<String, BTreeMap<String, Vec<Location>>>,
+}
+
+impl DependencyGraph {
+    pub fn build(variables: &[CssVariable]) -> Self {
+        let mut edges: HashMap<String, BTreeMap<String, Vec<Location>>> = HashMap::new();
+        for variable in variables {
+            let location = Location::new(
+                variable.uri.clone(),
+                variable.name_range.unwrap_or(variable.range),
+            );
+            for reference in var_references(&variable.value) {
+                edges
+                    .entry(variable.name.clone())
+                    .or_default()
+                    .entry(reference)
+                    .or_default()
+                    .push(location.clone());
+            }
+        }
+        Self { edges }
+    }
+
+    /// Names directly referenced by `name`'s definitions
+    pub fn dependencies(&self, name: &str) -> Vec<&str> {
+        self.edges
+            .get(name)
+            .map(|targets| targets.keys().map(|s| s.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Definitions of `from` that reference `to`
+    pub fn reference_locations(&self, from: &str, to: &str) -> &[Location] {
+        self.edges
+            .get(from)
+            .and_then(|targets| targets.get(to))
+            .map(|locations| locations.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Shortest reference chain from `name` back to itself
+    /// (e.g. `["--a", "--b", "--a"]`), or `None` when `name` is not in a cycle.
+    pub fn cycle_through(&self, name: &str) -> Option<Vec<String>> {
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut queue: VecDeque<&str> = VecDeque::new();
+
+        for next in self.dependencies(name) {
+            if next == name {
+                return Some(vec![name.to_string(), name.to_string()]);
+            }
+            if visited.insert(next) {
+                previous.insert(next, name);
+                queue.push_back(next);
+            }
+        }
+
+        while let Some(current) = queue.pop_front() {
+            for next in self.dependencies(current) {
+                if next == name {
+                    let mut chain = vec![current.to_string()];
+                    let mut node = current;
+                    while let Some(&prev) = previous.get(node) {
+                        chain.push(prev.to_string());
+                        node = prev;
+                    }
+                    chain.reverse();
+                    chain.push(name.to_string());
+                    return Some(chain);
+                }
+                if visited.insert(next) {
+                    previous.insert(next, current);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_var_calls_into_names_and_fallbacks() {
+        let value = "calc(var(--a) * 2) var( --b , 1px solid var(--c) )";
+        let calls = var_calls(value);
+        assert_eq!(calls.len(), 2);
+        assert_eq!((calls[0].name, calls[0].fallback), ("--a", None));
+        assert_eq!(&value[calls[0].span.clone()], "var(--a)");
+        assert_eq!(
+            (calls[1].name, calls[1].fallback),
+            ("--b", Some("1px solid var(--c)"))
+        );
+        assert_eq!(
+            var_calls("var(--a, rgb(0 0 0 / 50%))")[0].fallback,
+            Some("rgb(0 0 0 / 50%)")
+        );
+        assert_eq!(var_calls("var(--a,)")[0].fallback, Some(""));
+        assert!(var_calls("1px solid red").is_empty());
+    }
+
+    #[test]
+    fn collects_references_in_fallbacks_and_calc() {
+        assert_eq!(
+            var_references("calc(var(--a) + var(--b, var(--c)))"),
+            vec!["--a", "--b", "--c"]
+        );
+        assert!(var_references("1px solid red").is_empty());
+    }
+
+    #[test]
+    fn finds_cycles_with_their_chain() {
+        let graph = DependencyGraph::build(&[
+            CssVariable::for_test("--a", "var(--b)"),
+            CssVariable::for_test("--b", "calc(var(--c) * 2)"),
+            CssVariable::for_test("--c", "var(--missing, var(--a))"),
+            CssVariable::for_test("--d", "var(--a)"),
+            CssVariable::for_test("--self", "var(--self)"),
+        ]);
+
+        assert_eq!(
+            graph.cycle_through("--a").unwrap(),
+            vec!["--a", "--b", "--c", "--a"]
+        );
+        assert_eq!(
+            graph.cycle_through("--c").unwrap(),
+            vec!["--c", "--a", "--b", "--c"]
+        );
+        assert_eq!(
+            graph.cycle_through("--self").unwrap(),
+            vec!["--self", "--self"]
+        );
+        assert!(graph.cycle_through("--d").is_none());
+        assert_eq!(graph.reference_locations("--c", "--a").len(), 1);
+    }
+}
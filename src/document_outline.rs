@@ -0,0 +1,247 @@
+use tower_lsp::lsp_types::{DocumentSymbol, Range, SymbolKind};
+
+use crate::dom_tree::DomTree;
+use crate::parsers::stylesheet::{parse_stylesheet, Node, Stylesheet};
+use crate::parsers::tokenizer::Token;
+use crate::types::{offset_to_position, DOMNodeInfo};
+
+/// Outline of a document's custom properties, nested under the rules and
+/// at-rules that contain them (e.g. `@media (...) > :root > --primary`).
+/// Rules and at-rules without custom properties are left out. In HTML-like
+/// documents, `<style>` blocks contribute their rules and each `style`
+/// attribute becomes a symbol named after its element.
+pub fn document_outline(text: &str, is_html: bool) -> Vec<DocumentSymbol> {
+    if !is_html {
+        let sheet = parse_stylesheet(text);
+        return outline_nodes(&Segment::new(text, 0, &sheet), &sheet.nodes);
+    }
+
+    let parsed = DomTree::parse(text);
+    let mut symbols = Vec::new();
+    for block in &parsed.style_blocks {
+        let sheet = parse_stylesheet(&block.content);
+        let segment = Segment::new(text, block.content_start, &sheet);
+        symbols.extend(outline_nodes(&segment, &sheet.nodes));
+    }
+    for inline in &parsed.inline_styles {
+        let sheet = parse_stylesheet(&inline.value);
+        let segment = Segment::new(text, inline.value_start, &sheet);
+        let children = outline_nodes(&segment, &sheet.nodes);
+        if children.is_empty() {
+            continue;
+        }
+        let name = parsed
+            .dom_tree
+            .find_node_at_position(inline.attribute_start)
+            .map(|node| element_label(&node))
+            .unwrap_or_else(|| "element".to_string());
+        let end = inline.value_start + inline.value.len();
+        symbols.push(container(
+            name,
+            Some("style attribute".to_string()),
+            SymbolKind::OBJECT,
+            segment.range(inline.attribute_start, end),
+            segment.range(inline.attribute_start, inline.value_start),
+            children,
+        ));
+    }
+    symbols.sort_by_key(|symbol| symbol.range.start);
+    symbols
+}
+
+/// A parsed CSS region of the document and where it starts
+struct Segment<'a> {
+    text: &'a str,
+    base: usize,
+    tokens: &'a [Token],
+}
+
+impl<'a> Segment<'a> {
+    fn new(text: &'a str, base: usize, sheet: &'a Stylesheet) -> Self {
+        Self {
+            text,
+            base,
+            tokens: &sheet.tokens,
+        }
+    }
+
+    /// Range of a span relative to the document (`absolute` offsets)
+    fn range(&self, start: usize, end: usize) -> Range {
+        Range::new(
+            offset_to_position(self.text, start),
+            offset_to_position(self.text, end),
+        )
+    }
+
+    /// Range of a span relative to this segment
+    fn local_range(&self, span: &std::ops::Range<usize>) -> Range {
+        self.range(self.base + span.start, self.base + span.end)
+    }
+
+    /// Span covered by a range of tokens, relative to this segment
+    fn token_span(&self, tokens: &std::ops::Range<usize>) -> Option<std::ops::Range<usize>> {
+        if tokens.is_empty() {
+            return None;
+        }
+        Some(self.tokens[tokens.start].start..self.tokens[tokens.end - 1].end)
+    }
+}
+
+fn outline_nodes(segment: &Segment, nodes: &[Node]) -> Vec<DocumentSymbol> {
+    let mut symbols = Vec::new();
+    for node in nodes {
+        match node {
+            Node::Declaration(declaration) if declaration.is_custom_property() => {
+                let value_start = segment.base + declaration.value_span.start;
+                let value_end = segment.base + declaration.value_span.end;
+                #[allow(deprecated)]
+                symbols.push(DocumentSymbol {
+                    name: declaration.name.clone(),
+                    detail: Some(segment.text[value_start..value_end].to_string()),
+                    kind: SymbolKind::VARIABLE,
+                    tags: None,
+                    deprecated: None,
+                    range: segment.local_range(&declaration.span),
+                    selection_range: segment.local_range(&declaration.name_span),
+                    children: None,
+                });
+            }
+            Node::Declaration(_) => {}
+            Node::StyleRule(rule) => {
+                let children = outline_nodes(segment, &rule.children);
+                if children.is_empty() {
+                    continue;
+                }
+                let selection = segment
+                    .token_span(&rule.prelude_tokens)
+                    .unwrap_or(rule.span.start..rule.span.start);
+                symbols.push(container(
+                    rule.selector.clone(),
+                    None,
+                    SymbolKind::CLASS,
+                    segment.local_range(&rule.span),
+                    segment.local_range(&selection),
+                    children,
+                ));
+            }
+            Node::AtRule(at_rule) => {
+                let Some(nodes) = &at_rule.children else {
+                    continue;
+                };
+                let children = outline_nodes(segment, nodes);
+                if children.is_empty() {
+                    continue;
+                }
+                let name = if at_rule.prelude.is_empty() {
+                    format!("@{}", at_rule.name)
+                } else {
+                    format!("@{} {}", at_rule.name, at_rule.prelude)
+                };
+                let selection_end = segment
+                    .token_span(&at_rule.prelude_tokens)
+                    .map(|span| span.end)
+                    .unwrap_or(at_rule.span.start + at_rule.name.len() + 1);
+                symbols.push(container(
+                    name,
+                    None,
+                    SymbolKind::NAMESPACE,
+                    segment.local_range(&at_rule.span),
+                    segment.local_range(&(at_rule.span.start..selection_end)),
+                    children,
+                ));
+            }
+        }
+    }
+    symbols
+}
+
+#[allow(deprecated)]
+fn container(
+    name: String,
+    detail: Option<String>,
+    kind: SymbolKind,
+    range: Range,
+    selection_range: Range,
+    children: Vec<DocumentSymbol>,
+) -> DocumentSymbol {
+    DocumentSymbol {
+        name,
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range,
+        children: Some(children),
+    }
+}
+
+/// Element label such as `div#main.card`
+fn element_label(node: &DOMNodeInfo) -> String {
+    let mut label = node.tag.clone();
+    if let Some(id) = &node.id {
+        label.push_str(&format!("#{}", id));
+    }
+    for class in &node.classes {
+        label.push_str(&format!(".{}", class));
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (name, children) pairs, recursively
+    fn shape(symbols: &[DocumentSymbol]) -> Vec<String> {
+        symbols
+            .iter()
+            .map(|symbol| match &symbol.children {
+                Some(children) => format!("{} > [{}]", symbol.name, shape(children).join(", ")),
+                None => symbol.name.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nests_definitions_under_rules_and_at_rules() {
+        let text = ":root { --primary: #fff; color: red; }\n\
+                    [data-theme=dark] { --primary: #111; }\n\
+                    .plain { color: blue; }\n\
+                    @media (min-width: 768px) {\n  @layer base {\n    :root { --gap: 2rem; }\n  }\n}\n\
+                    .card { & .title { --size: 2; } }";
+        let outline = document_outline(text, false);
+        assert_eq!(
+            shape(&outline),
+            vec![
+                ":root > [--primary]",
+                "[data-theme=dark] > [--primary]",
+                "@media (min-width: 768px) > [@layer base > [:root > [--gap]]]",
+                ".card > [& .title > [--size]]",
+            ]
+        );
+
+        let primary = &outline[0].children.as_ref().unwrap()[0];
+        assert_eq!(primary.detail.as_deref(), Some("#fff"));
+        assert_eq!(primary.selection_range.start.character, 8);
+        assert_eq!(primary.selection_range.end.character, 17);
+        assert_eq!(outline[0].selection_range.end.character, 5);
+        assert_eq!(outline[2].selection_range.end.character, 25);
+    }
+
+    #[test]
+    fn outlines_html_style_blocks_and_attributes() {
+        let html = "<style>\n  :root { --bg: #fff; }\n</style>\n\
+                    <div id=\"main\" class=\"card\" style=\"--accent: red; color: var(--bg)\"></div>\n\
+                    <p style=\"color: red\"></p>";
+        let outline = document_outline(html, true);
+        assert_eq!(
+            shape(&outline),
+            vec![":root > [--bg]", "div#main.card > [--accent]"]
+        );
+        let accent = &outline[1].children.as_ref().unwrap()[0];
+        assert_eq!(accent.selection_range.start.line, 3);
+        let start = crate::types::position_to_offset(html, accent.selection_range.start).unwrap();
+        assert!(html[start..].starts_with("--accent"));
+    }
+}
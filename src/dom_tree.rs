@@ -1,3 +1,4 @@
+use crate::specificity::split_selector_list;
 use crate::types::DOMNodeInfo;
 
 #[derive(Debug, Clone)]
@@ -299,6 +300,10 @@ impl DomTree {
         Some(self.to_info(idx))
     }
 
+    pub fn parent(&self, node_index: usize) -> Option<usize> {
+        self.nodes.get(node_index)?.parent
+    }
+
     pub fn matches_selector(&self, node_index: usize, selector: &str) -> bool {
         let selector = selector.trim();
         if selector.is_empty() {
@@ -307,8 +312,7 @@ impl DomTree {
         if selector == ":root" {
             return true;
         }
-        let selectors: Vec<&str> = selector.split(',').map(|s| s.trim()).collect();
-        for sel in selectors {
+        for sel in split_selector_list(selector) {
             if sel.is_empty() {
                 continue;
             }
@@ -473,11 +477,7 @@ fn parse_simple_selector(token: &str) -> SimpleSelector {
 
     if !current.is_empty() {
         match mode {
-            't' => {
-                if current != "*" {
-                    tag = Some(current);
-                }
-            }
+            't' if current != "*" => tag = Some(current),
             'i' => id = Some(current),
             'c' => classes.push(current),
             _ => {}
@@ -0,0 +1,115 @@
+/// Bonus for matching the first character of the name (after `--`)
+const START_BONUS: i32 = 8;
+
+/// Bonus for matching the first character of a dash- or underscore-separated
+/// segment, so `prcol` prefers `--primary-color` over `--progress-cool`
+const SEGMENT_BONUS: i32 = 6;
+
+/// Bonus for matching right after the previous matched character
+const CONSECUTIVE_BONUS: i32 = 4;
+
+/// Bonus when the query spells out the whole name
+const EXACT_BONUS: i32 = 20;
+
+/// Penalty per skipped character between two matched characters
+const GAP_PENALTY: i32 = 1;
+
+/// Score how well `query` fuzzily matches a custom property name, or `None`
+/// when its characters do not appear in order. Matching ignores case and the
+/// leading `--` of both strings; higher scores are better matches and an
+/// empty query matches everything with a score of 0.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let query: Vec<char> = strip_dashes(query).to_lowercase().chars().collect();
+    let name: Vec<char> = strip_dashes(candidate).chars().collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let lower: Vec<char> = name
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    if query.len() > lower.len() {
+        return None;
+    }
+
+    // best[j] is the best score of the query prefix so far with its last
+    // character matched at name[j].
+    let mut best: Vec<Option<i32>> = lower
+        .iter()
+        .enumerate()
+        .map(|(j, c)| (*c == query[0]).then(|| 1 + position_bonus(&name, j)))
+        .collect();
+
+    for q in &query[1..] {
+        let mut next = vec![None; lower.len()];
+        for j in 1..lower.len() {
+            if lower[j] != *q {
+                continue;
+            }
+            let previous = (0..j)
+                .filter_map(|k| {
+                    let score = best[k]?;
+                    let link = if k + 1 == j {
+                        CONSECUTIVE_BONUS
+                    } else {
+                        -GAP_PENALTY * (j - k - 1) as i32
+                    };
+                    Some(score + link)
+                })
+                .max();
+            next[j] = previous.map(|score| score + 1 + position_bonus(&name, j));
+        }
+        best = next;
+    }
+
+    let score = best.into_iter().flatten().max()?;
+    if query == lower {
+        Some(score + EXACT_BONUS)
+    } else {
+        Some(score)
+    }
+}
+
+fn strip_dashes(text: &str) -> &str {
+    let text = text.trim();
+    text.strip_prefix("--").unwrap_or(text)
+}
+
+fn position_bonus(name: &[char], idx: usize) -> i32 {
+    if idx == 0 {
+        START_BONUS
+    } else if matches!(name[idx - 1], '-' | '_') {
+        SEGMENT_BONUS
+    } else if name[idx].is_uppercase() && name[idx - 1].is_lowercase() {
+        // camelCase names such as `--fontSize`
+        SEGMENT_BONUS
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subsequences_across_segments() {
+        assert!(fuzzy_score("prcol", "--primary-color").is_some());
+        assert!(fuzzy_score("--pc", "--primary-color").is_some());
+        assert!(fuzzy_score("PRIMARY", "--primary-color").is_some());
+        assert!(fuzzy_score("fs", "--fontSize").is_some());
+        assert_eq!(fuzzy_score("", "--anything"), Some(0));
+        assert_eq!(fuzzy_score("colp", "--primary-color"), None);
+        assert_eq!(fuzzy_score("primary-colors", "--primary-color"), None);
+    }
+
+    #[test]
+    fn ranks_segment_starts_and_exact_matches_higher() {
+        let score = |query, name| fuzzy_score(query, name).unwrap();
+        assert!(score("prcol", "--primary-color") > score("prcol", "--progress-cool"));
+        assert!(score("bg", "--bg-surface") > score("bg", "--hover-bg"));
+        assert!(score("bg", "--hover-bg") > score("bg", "--debug"));
+        assert!(score("gap", "--gap") > score("gap", "--gap-lg"));
+        assert!(score("color", "--color-text") > score("color", "--text-color"));
+    }
+}
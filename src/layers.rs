@@ -0,0 +1,278 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use tower_lsp::lsp_types::Url;
+
+use crate::types::CssVariable;
+
+/// Cascade layers declared by one document
+#[derive(Debug, Clone, Default)]
+pub struct DocumentLayers {
+    /// Full dotted layer names (e.g., "components.buttons") in order of first declaration
+    pub names: Vec<String>,
+
+    /// Stylesheets imported into a layer with `@import url(...) layer(name)`
+    pub imports: Vec<(Url, String)>,
+}
+
+impl DocumentLayers {
+    pub fn declare(&mut self, name: String) {
+        if !self.names.contains(&name) {
+            self.names.push(name);
+        }
+    }
+
+    pub fn extend(&mut self, other: DocumentLayers) {
+        for name in other.names {
+            self.declare(name);
+        }
+        self.imports.extend(other.imports);
+    }
+}
+
+/// Name given to an anonymous `@layer { ... }` block at a byte offset.
+pub fn anonymous_layer_name(offset: usize) -> String {
+    format!("<anonymous@{}>", offset)
+}
+
+/// Layer name for display, with anonymous layers shown as "(anonymous)".
+pub fn display_layer_name(name: &str) -> String {
+    name.split('.')
+        .map(|segment| {
+            if segment.starts_with("<anonymous") {
+                "(anonymous)"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Join a parent layer and a child layer name into a full dotted name.
+pub fn join_layer_name(parent: Option<&str>, name: &str) -> String {
+    match parent {
+        Some(parent) if !parent.is_empty() => format!("{}.{}", parent, name),
+        _ => name.to_string(),
+    }
+}
+
+/// Workspace-wide cascade layer order merged from every document's declarations.
+#[derive(Debug, Clone, Default)]
+pub struct LayerOrder {
+    /// Parent layer name ("" for the top level) -> child segments in cascade order
+    children: HashMap<String, Vec<String>>,
+
+    /// Imported stylesheet -> layer it was imported into
+    imports: HashMap<Url, String>,
+}
+
+impl LayerOrder {
+    /// Merge per-document layer orders. Each document contributes "declared
+    /// before" constraints, so `@layer reset, tokens;` in one file orders
+    /// `@layer tokens { ... }` blocks in other files.
+    pub fn build(documents: &[(&Url, &DocumentLayers)]) -> Self {
+        let mut documents = documents.to_vec();
+        documents.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+
+        let mut sequences: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+        let mut imports = HashMap::new();
+        for (_, layers) in &documents {
+            let mut per_parent: HashMap<String, Vec<String>> = HashMap::new();
+            for name in &layers.names {
+                let mut parent = String::new();
+                for segment in name.split('.') {
+                    let children = per_parent.entry(parent.clone()).or_default();
+                    if !children.iter().any(|c| c == segment) {
+                        children.push(segment.to_string());
+                    }
+                    parent = join_layer_name(Some(&parent), segment);
+                }
+            }
+            for (parent, children) in per_parent {
+                sequences.entry(parent).or_default().push(children);
+            }
+            for (url, layer) in &layers.imports {
+                imports.insert(url.clone(), layer.clone());
+            }
+        }
+
+        let children = sequences
+            .into_iter()
+            .map(|(parent, seqs)| (parent, merge_sequences(&seqs)))
+            .collect();
+        Self { children, imports }
+    }
+
+    /// Effective layer of a definition, including a layer its stylesheet was imported into.
+    pub fn layer_name(&self, variable: &CssVariable) -> Option<String> {
+        match (self.imports.get(&variable.uri), variable.layer.as_deref()) {
+            (Some(outer), Some(inner)) => Some(join_layer_name(Some(outer), inner)),
+            (Some(outer), None) => Some(outer.clone()),
+            (None, inner) => inner.map(|s| s.to_string()),
+        }
+    }
+
+    /// Sort key where a larger key wins for normal declarations. Unlayered
+    /// declarations rank above every layer, and a layer's own declarations
+    /// rank above its sub-layers.
+    fn rank(&self, variable: &CssVariable) -> Vec<usize> {
+        let name = match self.layer_name(variable) {
+            Some(name) => name,
+            None => return vec![usize::MAX],
+        };
+
+        let mut key = Vec::new();
+        let mut parent = String::new();
+        for segment in name.split('.') {
+            let siblings = self.children.get(&parent);
+            let index = siblings
+                .and_then(|s| s.iter().position(|c| c == segment))
+                .unwrap_or_else(|| siblings.map(|s| s.len()).unwrap_or(0));
+            key.push(index);
+            parent = join_layer_name(Some(&parent), segment);
+        }
+        key.push(usize::MAX);
+        key
+    }
+
+    /// Compare two definitions of equal importance by layer, winner first.
+    /// Layer precedence is reversed for `!important` declarations.
+    pub fn compare(&self, a: &CssVariable, b: &CssVariable) -> Ordering {
+        let rank_a = self.rank(a);
+        let rank_b = self.rank(b);
+        if a.important && b.important {
+            rank_a.cmp(&rank_b)
+        } else {
+            rank_b.cmp(&rank_a)
+        }
+    }
+}
+
+/// Merge ordered sequences into one order that respects every sequence where
+/// possible, breaking ties by first appearance.
+fn merge_sequences(sequences: &[Vec<String>]) -> Vec<String> {
+    let mut first_seen: Vec<String> = Vec::new();
+    let mut edges: HashMap<&str, HashSet<&str>> = HashMap::new();
+    let mut indegree: HashMap<&str, usize> = HashMap::new();
+
+    for seq in sequences {
+        for item in seq {
+            if !first_seen.contains(item) {
+                first_seen.push(item.clone());
+            }
+            indegree.entry(item.as_str()).or_insert(0);
+        }
+        for pair in seq.windows(2) {
+            if edges
+                .entry(pair[0].as_str())
+                .or_default()
+                .insert(pair[1].as_str())
+            {
+                *indegree.entry(pair[1].as_str()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut order = Vec::new();
+    let mut placed: HashSet<&str> = HashSet::new();
+    while order.len() < first_seen.len() {
+        let next = first_seen
+            .iter()
+            .find(|item| !placed.contains(item.as_str()) && indegree[item.as_str()] == 0)
+            // A cycle between documents: fall back to first appearance.
+            .or_else(|| {
+                first_seen
+                    .iter()
+                    .find(|item| !placed.contains(item.as_str()))
+            })
+            .unwrap();
+        placed.insert(next.as_str());
+        order.push(next.clone());
+        if let Some(targets) = edges.get(next.as_str()) {
+            for target in targets {
+                if let Some(count) = indegree.get_mut(target) {
+                    *count = count.saturating_sub(1);
+                }
+            }
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(names: &[&str]) -> DocumentLayers {
+        DocumentLayers {
+            names: names.iter().map(|s| s.to_string()).collect(),
+            imports: Vec::new(),
+        }
+    }
+
+    fn variable(layer: Option<&str>, important: bool) -> CssVariable {
+        CssVariable {
+            uri: Url::parse("file:///b.css").unwrap(),
+            layer: layer.map(|s| s.to_string()),
+            important,
+            ..CssVariable::for_test("--x", layer.unwrap_or("unlayered"))
+        }
+    }
+
+    #[test]
+    fn merges_layer_order_across_documents() {
+        let a = Url::parse("file:///a.css").unwrap();
+        let b = Url::parse("file:///b.css").unwrap();
+        let decl = layers(&["reset", "tokens", "components", "overrides"]);
+        let usage = layers(&["components", "tokens"]);
+        // b.css mentions components first, but a.css declares the order.
+        let order = LayerOrder::build(&[(&b, &usage), (&a, &decl)]);
+        assert_eq!(
+            order.children[""],
+            vec!["reset", "tokens", "components", "overrides"]
+        );
+    }
+
+    #[test]
+    fn later_layers_and_unlayered_win_for_normal_declarations() {
+        let a = Url::parse("file:///a.css").unwrap();
+        let decl = layers(&["reset", "tokens", "components", "components.buttons"]);
+        let order = LayerOrder::build(&[(&a, &decl)]);
+
+        let tokens = variable(Some("tokens"), false);
+        let components = variable(Some("components"), false);
+        let buttons = variable(Some("components.buttons"), false);
+        let unlayered = variable(None, false);
+
+        assert_eq!(order.compare(&components, &tokens), Ordering::Less);
+        assert_eq!(order.compare(&unlayered, &components), Ordering::Less);
+        assert_eq!(order.compare(&components, &buttons), Ordering::Less);
+    }
+
+    #[test]
+    fn important_declarations_reverse_layer_order() {
+        let a = Url::parse("file:///a.css").unwrap();
+        let order = LayerOrder::build(&[(&a, &layers(&["reset", "overrides"]))]);
+
+        let reset = variable(Some("reset"), true);
+        let overrides = variable(Some("overrides"), true);
+        let unlayered = variable(None, true);
+
+        assert_eq!(order.compare(&reset, &overrides), Ordering::Less);
+        assert_eq!(order.compare(&overrides, &unlayered), Ordering::Less);
+    }
+
+    #[test]
+    fn imported_stylesheets_inherit_their_layer() {
+        let a = Url::parse("file:///a.css").unwrap();
+        let b = Url::parse("file:///b.css").unwrap();
+        let mut decl = layers(&["reset", "theme"]);
+        decl.imports.push((b.clone(), "theme".to_string()));
+        let order = LayerOrder::build(&[(&a, &decl)]);
+
+        let imported = variable(None, false);
+        assert_eq!(order.layer_name(&imported).as_deref(), Some("theme"));
+        assert_eq!(display_layer_name("<anonymous@4>.x"), "(anonymous).x");
+    }
+}
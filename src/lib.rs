@@ -1,13 +1,24 @@
 // Library interface for css-variable-lsp
 // This allows integration tests and external usage
 
+pub mod code_actions;
 pub mod color;
+pub mod css_math;
+pub mod dependency_graph;
+pub mod document_outline;
 pub mod dom_tree;
+pub mod fuzzy;
+pub mod layers;
 pub mod lsp_server;
+pub mod lsp_service;
 pub mod manager;
 pub mod parsers;
 pub mod path_display;
+pub mod property_syntax;
+pub mod rename;
 pub mod runtime_config;
+pub mod semantic_tokens;
 pub mod specificity;
+pub mod token_match;
 pub mod types;
 pub mod workspace;
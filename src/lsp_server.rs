@@ -1,35 +1,114 @@
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
+use tower_lsp::lsp_types::request::{GotoDeclarationParams, GotoDeclarationResponse};
 use tower_lsp::lsp_types::{
-    ColorInformation, ColorPresentation, ColorPresentationParams, ColorProviderCapability,
+    CodeAction, CodeActionKind, CodeActionOptions, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, CodeActionResponse, CodeLens, CodeLensOptions, CodeLensParams,
+    ColorInformation, ColorPresentation, ColorPresentationParams, ColorProviderCapability, Command,
     CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
-    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
-    DidChangeWorkspaceFoldersParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
-    DocumentColorParams, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse,
-    FileChangeType, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
-    HoverParams, InitializeParams, InitializeResult, Location, MarkupContent, MarkupKind,
-    MessageType, OneOf, Position, Range, ReferenceParams, RenameParams, ServerCapabilities,
-    SymbolInformation, SymbolKind, TextDocumentContentChangeEvent, TextDocumentSyncCapability,
-    TextDocumentSyncKind, TextEdit, Url, WorkDoneProgressOptions, WorkspaceEdit, WorkspaceFolder,
-    WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities, WorkspaceSymbolParams,
+    DeclarationCapability, Diagnostic, DiagnosticOptions, DiagnosticRelatedInformation,
+    DiagnosticServerCapabilities, DiagnosticSeverity, DiagnosticTag, DidChangeTextDocumentParams,
+    DidChangeWatchedFilesParams, DidChangeWorkspaceFoldersParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentColorParams, DocumentDiagnosticParams,
+    DocumentDiagnosticReport, DocumentDiagnosticReportResult, DocumentHighlight,
+    DocumentHighlightKind, DocumentHighlightParams, DocumentSymbolParams, DocumentSymbolResponse,
+    ExecuteCommandOptions, ExecuteCommandParams, FileChangeType, FullDocumentDiagnosticReport,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    InitializeParams, InitializeResult, InlayHint, InlayHintLabel, InlayHintParams,
+    InlayHintTooltip, Location, LocationLink, MarkupContent, MarkupKind, MessageType,
+    NumberOrString, OneOf, Position, PrepareRenameResponse, Range, ReferenceParams,
+    RelatedFullDocumentDiagnosticReport, RelatedUnchangedDocumentDiagnosticReport, RenameOptions,
+    RenameParams, SemanticTokens, SemanticTokensFullOptions, SemanticTokensOptions,
+    SemanticTokensParams, SemanticTokensRangeParams, SemanticTokensRangeResult,
+    SemanticTokensResult, ServerCapabilities, SymbolInformation, SymbolKind,
+    TextDocumentContentChangeEvent, TextDocumentPositionParams, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextEdit, UnchangedDocumentDiagnosticReport, Url,
+    WorkDoneProgressOptions, WorkspaceDiagnosticParams, WorkspaceDiagnosticReport,
+    WorkspaceDiagnosticReportResult, WorkspaceDocumentDiagnosticReport, WorkspaceEdit,
+    WorkspaceFolder, WorkspaceFoldersServerCapabilities, WorkspaceFullDocumentDiagnosticReport,
+    WorkspaceLocation, WorkspaceServerCapabilities, WorkspaceSymbol, WorkspaceSymbolOptions,
+    WorkspaceSymbolParams, WorkspaceSymbolResponse, WorkspaceUnchangedDocumentDiagnosticReport,
 };
 use tower_lsp::{Client, LanguageServer};
 
+use crate::code_actions::{
+    covers_whole_tokens, declaration_removal_range, declaration_values, find_literal_occurrences,
+    root_insertion_edit, suggest_variable_name, CIRCULAR_REFERENCE_CODE, EXTRACT_ALL_ACTION,
+    HARDCODED_VALUE_CODE, PLACEHOLDER_VALUE, REGISTERED_SYNTAX_CODE, UNDEFINED_FALLBACK_CODE,
+    UNDEFINED_VARIABLE_CODE, UNDEFINED_VARIABLE_FALLBACK_CODE, UNUSED_VARIABLE_CODE,
+};
 use crate::color::{generate_color_presentations, parse_color};
+use crate::css_math::evaluate_math;
+use crate::dependency_graph::{var_calls, var_references, DependencyGraph};
+use crate::document_outline::document_outline;
+use crate::fuzzy::fuzzy_score;
+use crate::layers::display_layer_name;
 use crate::manager::CssVariableManager;
 use crate::parsers::{parse_css_document, parse_html_document};
 use crate::path_display::{format_uri_for_display, to_normalized_fs_path, PathDisplayOptions};
-use crate::runtime_config::RuntimeConfig;
+use crate::property_syntax::PropertySyntax;
+use crate::rename::{unescape_identifier, validate_custom_property_name};
+use crate::runtime_config::{RenameCollision, RuntimeConfig};
+use crate::semantic_tokens::{self, is_third_party, ClassifiedName};
 use crate::specificity::{
-    calculate_specificity, compare_specificity, format_specificity, matches_context,
-    sort_by_cascade,
+    calculate_specificity, compare_specificity, filter_by_dom_match, format_specificity,
+    is_conditional, matches_context, sort_by_cascade_with_layers,
+};
+use crate::token_match::{find_hardcoded_values, TokenIndex};
+use crate::types::{
+    format_conditions, position_to_offset, Config, CssVariable, CssVariableUsage,
+    RegisteredProperty, ResolvedValue,
 };
-use crate::types::{position_to_offset, Config};
+use crate::workspace::collect_workspace_files;
+
+/// Command run by code lenses. It takes one `ShowReferencesArguments` object
+/// and returns `{ uri, position, locations }`: the lens's document and
+/// position, and the usages or overriding definitions of the variable. The
+/// client shows them in its references view (in VS Code, by converting them
+/// and passing them to `editor.action.showReferences`).
+pub const SHOW_REFERENCES_COMMAND: &str = "css-variable-lsp.showReferences";
+
+/// Most results returned for a workspace symbol query
+const MAX_WORKSPACE_SYMBOLS: usize = 200;
+
+/// Separates the selector from the path in a workspace symbol's container
+const CONTAINER_SEPARATOR: &str = " · ";
+
+/// Argument of `SHOW_REFERENCES_COMMAND`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ShowReferencesArguments {
+    /// Variable name
+    name: String,
+
+    /// Document and position of the definition the lens is on
+    uri: Url,
+    position: Position,
+
+    /// "usages" or "overrides"
+    filter: String,
+}
+
+/// Workspace-wide state read by every document's diagnostics
+struct DiagnosticsContext {
+    config: Config,
+    has_related_info: bool,
+
+    /// Only built when circular-reference diagnostics are enabled
+    graph: Option<DependencyGraph>,
+
+    /// Only built when hardcoded-value diagnostics are enabled
+    token_index: Option<TokenIndex>,
+}
 
+#[derive(Clone)]
 pub struct CssVariableLsp {
     client: Client,
     manager: Arc<CssVariableManager>,
@@ -39,8 +118,16 @@ pub struct CssVariableLsp {
     root_folder_path: Arc<RwLock<Option<PathBuf>>>,
     has_workspace_folder_capability: Arc<RwLock<bool>>,
     has_diagnostic_related_information: Arc<RwLock<bool>>,
-    usage_regex: Regex,
-    var_usage_regex: Regex,
+    has_definition_link_support: Arc<RwLock<bool>>,
+    has_declaration_link_support: Arc<RwLock<bool>>,
+    has_code_lens_refresh_support: Arc<RwLock<bool>>,
+    has_pull_diagnostics_support: Arc<RwLock<bool>>,
+    has_diagnostic_refresh_support: Arc<RwLock<bool>>,
+    has_symbol_location_resolve_support: Arc<RwLock<bool>>,
+    /// Bumped when a change may affect diagnostics of other documents
+    diagnostics_revision: Arc<AtomicU64>,
+    /// Cache key and result ID of the last pulled diagnostics per document
+    diagnostics_cache: Arc<RwLock<HashMap<Url, (u64, String)>>>,
     var_partial_regex: Regex,
     style_attr_regex: Regex,
 }
@@ -57,8 +144,14 @@ impl CssVariableLsp {
             root_folder_path: Arc::new(RwLock::new(None)),
             has_workspace_folder_capability: Arc::new(RwLock::new(false)),
             has_diagnostic_related_information: Arc::new(RwLock::new(false)),
-            usage_regex: Regex::new(r"var\((--[\w-]+)(?:\s*,\s*[^)]+)?\)").unwrap(),
-            var_usage_regex: Regex::new(r"var\((--[\w-]+)\)").unwrap(),
+            has_definition_link_support: Arc::new(RwLock::new(false)),
+            has_declaration_link_support: Arc::new(RwLock::new(false)),
+            has_code_lens_refresh_support: Arc::new(RwLock::new(false)),
+            has_pull_diagnostics_support: Arc::new(RwLock::new(false)),
+            has_diagnostic_refresh_support: Arc::new(RwLock::new(false)),
+            has_symbol_location_resolve_support: Arc::new(RwLock::new(false)),
+            diagnostics_revision: Arc::new(AtomicU64::new(0)),
+            diagnostics_cache: Arc::new(RwLock::new(HashMap::new())),
             var_partial_regex: Regex::new(r"var\(\s*(--[\w-]*)$").unwrap(),
             style_attr_regex: Regex::new(r#"(?i)style\s*=\s*["'][^"']*:\s*[^"';]*$"#).unwrap(),
         }
@@ -97,44 +190,539 @@ impl CssVariableLsp {
         }
     }
 
+    /// Publish diagnostics for a document, unless the client pulls them
+    /// through `textDocument/diagnostic` instead.
     async fn validate_document_text(&self, uri: &Url, text: &str) {
+        if *self.has_pull_diagnostics_support.read().await {
+            return;
+        }
+        let context = self.diagnostics_context().await;
+        self.publish_document_diagnostics(uri, text, &context).await;
+    }
+
+    async fn publish_document_diagnostics(
+        &self,
+        uri: &Url,
+        text: &str,
+        context: &DiagnosticsContext,
+    ) {
+        let diagnostics = self.document_diagnostics(uri, text, context).await;
+        self.client
+            .publish_diagnostics(uri.clone(), diagnostics, None)
+            .await;
+    }
+
+    /// Workspace-wide inputs of `document_diagnostics`, built once per pass
+    /// over one or more documents.
+    async fn diagnostics_context(&self) -> DiagnosticsContext {
+        let config = self.manager.get_config().await;
+        let graph =
+            match config.diagnostic_severity(CIRCULAR_REFERENCE_CODE, DiagnosticSeverity::ERROR) {
+                Some(_) => Some(self.manager.get_dependency_graph().await),
+                None => None,
+            };
+        let hardcoded_enabled = config.token_suggestions
+            && config
+                .diagnostic_severity(HARDCODED_VALUE_CODE, DiagnosticSeverity::HINT)
+                .is_some();
+        let token_index = if hardcoded_enabled {
+            Some(TokenIndex::build(&self.manager.get_all_variables().await))
+        } else {
+            None
+        };
+        DiagnosticsContext {
+            config,
+            has_related_info: *self.has_diagnostic_related_information.read().await,
+            graph,
+            token_index,
+        }
+    }
+
+    /// Every diagnostic for a document: undefined variables, `@property`
+    /// syntax mismatches, cycles, and the opt-in unused and hardcoded-value
+    /// hints. Shared by the push and pull models.
+    async fn document_diagnostics(
+        &self,
+        uri: &Url,
+        text: &str,
+        context: &DiagnosticsContext,
+    ) -> Vec<Diagnostic> {
         let mut diagnostics = Vec::new();
-        let has_related_info = *self.has_diagnostic_related_information.read().await;
+        let has_related_info = context.has_related_info;
+        let config = &context.config;
 
-        for captures in self.usage_regex.captures_iter(text) {
-            let match_all = captures.get(0).unwrap();
-            let name = captures.get(1).unwrap().as_str();
-            let definitions = self.manager.get_variables(name).await;
-            if !definitions.is_empty() {
+        for usage in self.manager.get_document_usages(uri).await {
+            // A fallback only matters when the variable itself is undefined.
+            if !self.manager.get_variables(&usage.name).await.is_empty() {
                 continue;
             }
-            let range = Range::new(
-                crate::types::offset_to_position(text, match_all.start()),
-                crate::types::offset_to_position(text, match_all.end()),
-            );
+            let dead_fallbacks = match &usage.fallback {
+                Some(fallback) => self.undefined_fallback_variables(&fallback.text).await,
+                None => Vec::new(),
+            };
+            let fallback_severity =
+                config.diagnostic_severity(UNDEFINED_FALLBACK_CODE, DiagnosticSeverity::WARNING);
+            if let (Some(fallback), Some(severity)) = (&usage.fallback, fallback_severity) {
+                for fallback_name in &dead_fallbacks {
+                    diagnostics.push(Diagnostic {
+                        range: fallback.range,
+                        severity: Some(severity),
+                        code: Some(NumberOrString::String(UNDEFINED_FALLBACK_CODE.to_string())),
+                        code_description: None,
+                        source: Some("css-variable-lsp".to_string()),
+                        message: format!(
+                            "Fallback of '{}' uses CSS variable '{}', which is not defined in the workspace",
+                            usage.name, fallback_name
+                        ),
+                        related_information: None,
+                        tags: None,
+                        data: Some(serde_json::json!({ "name": fallback_name })),
+                    });
+                }
+            }
+
+            let usable_fallback = usage
+                .fallback
+                .as_ref()
+                .filter(|_| dead_fallbacks.is_empty());
+            let (code, default_severity, message) = match usable_fallback {
+                Some(fallback) => (
+                    UNDEFINED_VARIABLE_FALLBACK_CODE,
+                    DiagnosticSeverity::INFORMATION,
+                    format!(
+                        "CSS variable '{}' is not defined in the workspace; the fallback '{}' is used",
+                        usage.name, fallback.text
+                    ),
+                ),
+                None => (
+                    UNDEFINED_VARIABLE_CODE,
+                    DiagnosticSeverity::WARNING,
+                    format!(
+                        "CSS variable '{}' is not defined in the workspace",
+                        usage.name
+                    ),
+                ),
+            };
+            let Some(severity) = config.diagnostic_severity(code, default_severity) else {
+                continue;
+            };
             diagnostics.push(Diagnostic {
-                range,
-                severity: Some(DiagnosticSeverity::WARNING),
-                code: None,
+                range: usage.range,
+                severity: Some(severity),
+                code: Some(NumberOrString::String(code.to_string())),
                 code_description: None,
                 source: Some("css-variable-lsp".to_string()),
-                message: format!("CSS variable '{}' is not defined in the workspace", name),
+                message,
                 related_information: if has_related_info {
                     Some(Vec::new())
                 } else {
                     None
                 },
                 tags: None,
-                data: None,
+                data: Some(serde_json::json!({ "name": usage.name })),
             });
         }
 
-        self.client
-            .publish_diagnostics(uri.clone(), diagnostics, None)
+        let syntax_severity =
+            config.diagnostic_severity(REGISTERED_SYNTAX_CODE, DiagnosticSeverity::WARNING);
+        if let Some(severity) = syntax_severity {
+            for definition in self.manager.get_document_variables(uri).await {
+                let registrations = self
+                    .manager
+                    .get_registered_properties(&definition.name)
+                    .await;
+                let registration = match registrations.first() {
+                    Some(registration) => registration,
+                    None => continue,
+                };
+                let syntax = match registration
+                    .syntax
+                    .as_deref()
+                    .and_then(PropertySyntax::parse)
+                {
+                    Some(syntax) => syntax,
+                    None => continue,
+                };
+                if syntax.matches(&definition.value) {
+                    continue;
+                }
+                diagnostics.push(Diagnostic {
+                    range: definition.value_range.unwrap_or(definition.range),
+                    severity: Some(severity),
+                    code: Some(NumberOrString::String(REGISTERED_SYNTAX_CODE.to_string())),
+                    code_description: None,
+                    source: Some("css-variable-lsp".to_string()),
+                    message: format!(
+                        "Value '{}' does not match the registered syntax '{}' of '{}'",
+                        definition.value, syntax, definition.name
+                    ),
+                    related_information: if has_related_info {
+                        Some(vec![DiagnosticRelatedInformation {
+                            location: Location::new(registration.uri.clone(), registration.range),
+                            message: format!("'{}' is registered here", definition.name),
+                        }])
+                    } else {
+                        None
+                    },
+                    tags: None,
+                    data: None,
+                });
+            }
+        }
+
+        let circular_severity =
+            config.diagnostic_severity(CIRCULAR_REFERENCE_CODE, DiagnosticSeverity::ERROR);
+        if let (Some(severity), Some(graph)) = (circular_severity, &context.graph) {
+            for definition in self.manager.get_document_variables(uri).await {
+                let chain = match graph.cycle_through(&definition.name) {
+                    Some(chain) => chain,
+                    None => continue,
+                };
+                // Only definitions that make the first reference are part of the cycle.
+                if !var_references(&definition.value).contains(&chain[1]) {
+                    continue;
+                }
+                let related = chain
+                    .windows(2)
+                    .filter_map(|pair| {
+                        let location = graph.reference_locations(&pair[0], &pair[1]).first()?;
+                        Some(DiagnosticRelatedInformation {
+                            location: location.clone(),
+                            message: format!("'{}' references '{}'", pair[0], pair[1]),
+                        })
+                    })
+                    .collect();
+                diagnostics.push(Diagnostic {
+                    range: definition.name_range.unwrap_or(definition.range),
+                    severity: Some(severity),
+                    code: Some(NumberOrString::String(CIRCULAR_REFERENCE_CODE.to_string())),
+                    code_description: None,
+                    source: Some("css-variable-lsp".to_string()),
+                    message: format!(
+                        "Circular reference: {}. Every variable in the cycle is invalid.",
+                        chain.join(" → ")
+                    ),
+                    related_information: if has_related_info {
+                        Some(related)
+                    } else {
+                        None
+                    },
+                    tags: None,
+                    data: None,
+                });
+            }
+        }
+
+        let unused_severity =
+            config.diagnostic_severity(UNUSED_VARIABLE_CODE, DiagnosticSeverity::HINT);
+        if let Some(severity) = unused_severity.filter(|_| config.unused_variables) {
+            for definition in self.manager.get_unused_document_variables(uri).await {
+                diagnostics.push(Diagnostic {
+                    range: definition.name_range.unwrap_or(definition.range),
+                    severity: Some(severity),
+                    code: Some(NumberOrString::String(UNUSED_VARIABLE_CODE.to_string())),
+                    code_description: None,
+                    source: Some("css-variable-lsp".to_string()),
+                    message: format!(
+                        "CSS variable '{}' is never used in the workspace",
+                        definition.name
+                    ),
+                    related_information: None,
+                    tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                    data: None,
+                });
+            }
+        }
+
+        let hardcoded_severity =
+            config.diagnostic_severity(HARDCODED_VALUE_CODE, DiagnosticSeverity::HINT);
+        let hardcoded_severity = hardcoded_severity.filter(|_| config.token_suggestions);
+        if let (Some(severity), Some(index)) = (hardcoded_severity, &context.token_index) {
+            let is_html = is_html_like(&uri.path().to_lowercase());
+            for hardcoded in find_hardcoded_values(text, is_html, index) {
+                let literal = &text[hardcoded.span.clone()];
+                let names = hardcoded
+                    .candidates
+                    .iter()
+                    .map(|name| format!("'{}'", name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let noun = if hardcoded.candidates.len() == 1 {
+                    "variable"
+                } else {
+                    "variables"
+                };
+                diagnostics.push(Diagnostic {
+                    range: Range::new(
+                        crate::types::offset_to_position(text, hardcoded.span.start),
+                        crate::types::offset_to_position(text, hardcoded.span.end),
+                    ),
+                    severity: Some(severity),
+                    code: Some(NumberOrString::String(HARDCODED_VALUE_CODE.to_string())),
+                    code_description: None,
+                    source: Some("css-variable-lsp".to_string()),
+                    message: format!("Value '{}' matches CSS {} {}", literal, noun, names),
+                    related_information: None,
+                    tags: None,
+                    data: Some(serde_json::json!({ "candidates": hardcoded.candidates })),
+                });
+            }
+        }
+
+        // Stable order, so unchanged diagnostics keep their pull result ID.
+        diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+        diagnostics
+    }
+
+    /// Undefined variables a fallback depends on, in source order: every
+    /// `var()` in it (e.g. `--gone` in `1px solid var(--gone)`) that is
+    /// undefined and has no usable fallback of its own.
+    async fn undefined_fallback_variables(&self, fallback: &str) -> Vec<String> {
+        let mut undefined: Vec<String> = Vec::new();
+        let mut pending = VecDeque::from([fallback]);
+        while let Some(value) = pending.pop_front() {
+            for call in var_calls(value) {
+                if !self.manager.get_variables(call.name).await.is_empty() {
+                    continue;
+                }
+                match call.fallback {
+                    Some(next) => pending.push_back(next),
+                    None if !undefined.iter().any(|name| name == call.name) => {
+                        undefined.push(call.name.to_string())
+                    }
+                    None => {}
+                }
+            }
+        }
+        undefined
+    }
+
+    /// Pull report for a document that skips validation when neither the
+    /// document nor the rest of the workspace changed since the client got
+    /// `previous_result_id`. Closed files are read from disk only when they
+    /// are validated; `None` when such a file cannot be read.
+    async fn cached_diagnostic_report(
+        &self,
+        uri: &Url,
+        open_text: Option<&str>,
+        previous_result_id: Option<&str>,
+        context: &mut Option<DiagnosticsContext>,
+    ) -> Option<DocumentDiagnosticReport> {
+        // Closed files only change through watched-file events, which bump
+        // the revision, so only open documents add their text to the key.
+        let revision = self.diagnostics_revision.load(Ordering::SeqCst);
+        let mut hasher = DefaultHasher::new();
+        (revision, open_text).hash(&mut hasher);
+        let key = hasher.finish();
+
+        let cached = {
+            let cache = self.diagnostics_cache.read().await;
+            cache
+                .get(uri)
+                .filter(|(cached_key, _)| *cached_key == key)
+                .map(|(_, result_id)| result_id.clone())
+        };
+        if let Some(result_id) = cached.filter(|id| Some(id.as_str()) == previous_result_id) {
+            return Some(DocumentDiagnosticReport::Unchanged(
+                RelatedUnchangedDocumentDiagnosticReport {
+                    related_documents: None,
+                    unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                        result_id,
+                    },
+                },
+            ));
+        }
+
+        let text = match open_text {
+            Some(text) => text.to_string(),
+            None => tokio::fs::read_to_string(to_normalized_fs_path(uri)?)
+                .await
+                .ok()?,
+        };
+        if context.is_none() {
+            *context = Some(self.diagnostics_context().await);
+        }
+        let context = context.as_ref()?;
+        let report = self
+            .document_diagnostic_report(uri, &text, previous_result_id, context)
             .await;
+        let result_id = match &report {
+            DocumentDiagnosticReport::Full(report) => report
+                .full_document_diagnostic_report
+                .result_id
+                .clone()
+                .unwrap_or_default(),
+            DocumentDiagnosticReport::Unchanged(report) => report
+                .unchanged_document_diagnostic_report
+                .result_id
+                .clone(),
+        };
+        self.diagnostics_cache
+            .write()
+            .await
+            .insert(uri.clone(), (key, result_id));
+        Some(report)
+    }
+
+    /// Full report for a document, or an unchanged report when its
+    /// diagnostics still match `previous_result_id`.
+    async fn document_diagnostic_report(
+        &self,
+        uri: &Url,
+        text: &str,
+        previous_result_id: Option<&str>,
+        context: &DiagnosticsContext,
+    ) -> DocumentDiagnosticReport {
+        let diagnostics = self.document_diagnostics(uri, text, context).await;
+        let result_id = diagnostics_result_id(&diagnostics);
+        if previous_result_id == Some(result_id.as_str()) {
+            return DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+                related_documents: None,
+                unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                    result_id,
+                },
+            });
+        }
+        DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+            related_documents: None,
+            full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                result_id: Some(result_id),
+                items: diagnostics,
+            },
+        })
+    }
+
+    /// Revalidate open documents other than `changed`, whose unused-variable
+    /// and cycle diagnostics depend on the rest of the workspace.
+    async fn validate_dependent_documents(&self, changed: &Url) {
+        if *self.has_pull_diagnostics_support.read().await {
+            self.refresh_diagnostics().await;
+            return;
+        }
+        let docs_snapshot = {
+            let docs = self.document_map.read().await;
+            docs.iter()
+                .filter(|(uri, _)| *uri != changed)
+                .map(|(uri, text)| (uri.clone(), text.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        let context = self.diagnostics_context().await;
+        for (uri, text) in docs_snapshot {
+            self.publish_document_diagnostics(&uri, &text, &context)
+                .await;
+        }
+    }
+
+    /// After `uri` was reparsed, revalidate other documents and refresh code
+    /// lenses if what it contributes to the workspace changed, as given by
+    /// its fingerprint from before the update.
+    async fn propagate_document_update(&self, uri: &Url, previous_fingerprint: u64) {
+        if self.manager.document_fingerprint(uri).await == previous_fingerprint {
+            return;
+        }
+        self.diagnostics_revision.fetch_add(1, Ordering::SeqCst);
+        self.validate_dependent_documents(uri).await;
+        self.refresh_code_lenses().await;
+    }
+
+    /// Answer a `workspace/symbol` request. Clients that can resolve
+    /// `location.range` get symbols locating only their document, with the
+    /// range filled in by `workspaceSymbol/resolve`; other clients get full
+    /// locations. Served by `CssVariableService`, since
+    /// `LanguageServer::symbol` can only return `SymbolInformation`.
+    pub async fn workspace_symbols(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<WorkspaceSymbolResponse>> {
+        if !*self.has_symbol_location_resolve_support.read().await {
+            return Ok(self
+                .symbol(params)
+                .await?
+                .map(WorkspaceSymbolResponse::Flat));
+        }
+        let symbols = self
+            .ranked_symbols(&params.query)
+            .await
+            .into_iter()
+            .map(|(var, container_name)| WorkspaceSymbol {
+                name: var.name,
+                kind: SymbolKind::VARIABLE,
+                tags: None,
+                container_name: Some(container_name),
+                location: OneOf::Right(WorkspaceLocation { uri: var.uri }),
+                data: Some(serde_json::json!({ "selector": var.selector })),
+            })
+            .collect();
+        Ok(Some(WorkspaceSymbolResponse::Nested(symbols)))
+    }
+
+    /// Definitions matching a workspace symbol `query`, best first and
+    /// capped, with their container names
+    async fn ranked_symbols(&self, query: &str) -> Vec<(CssVariable, String)> {
+        let mut scored: Vec<(i32, CssVariable)> = self
+            .manager
+            .get_all_variables()
+            .await
+            .into_iter()
+            .filter_map(|var| Some((fuzzy_score(query, &var.name)?, var)))
+            .collect();
+        scored.sort_by(|(score_a, a), (score_b, b)| {
+            score_b
+                .cmp(score_a)
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.uri.as_str().cmp(b.uri.as_str()))
+                .then_with(|| a.range.start.cmp(&b.range.start))
+        });
+        scored.truncate(MAX_WORKSPACE_SYMBOLS);
+
+        let workspace_folder_paths = self.workspace_folder_paths.read().await.clone();
+        let root_folder_path = self.root_folder_path.read().await.clone();
+        scored
+            .into_iter()
+            .map(|(_, var)| {
+                let options = PathDisplayOptions {
+                    mode: self.runtime_config.path_display_mode,
+                    abbrev_length: self.runtime_config.path_display_abbrev_length,
+                    workspace_folder_paths: &workspace_folder_paths,
+                    root_folder_path: root_folder_path.as_ref(),
+                };
+                let container_name = symbol_container_name(&var.selector, &var.uri, options);
+                (var, container_name)
+            })
+            .collect()
+    }
+
+    /// Ask the client to re-request code lenses, whose usage and override
+    /// counts depend on every document in the workspace. Does not wait for
+    /// the client's response.
+    async fn refresh_code_lenses(&self) {
+        if *self.has_code_lens_refresh_support.read().await {
+            let client = self.client.clone();
+            tokio::spawn(async move {
+                let _ = client.code_lens_refresh().await;
+            });
+        }
+    }
+
+    /// Ask a pull-model client to re-request diagnostics after a change that
+    /// may affect other documents (e.g. a definition added or removed). Does
+    /// not wait for the client's response.
+    async fn refresh_diagnostics(&self) {
+        if *self.has_diagnostic_refresh_support.read().await {
+            let client = self.client.clone();
+            tokio::spawn(async move {
+                let _ = client.workspace_diagnostic_refresh().await;
+            });
+        }
     }
 
     async fn validate_all_open_documents(&self) {
+        self.diagnostics_revision.fetch_add(1, Ordering::SeqCst);
+        if *self.has_pull_diagnostics_support.read().await {
+            self.refresh_diagnostics().await;
+            return;
+        }
         let docs_snapshot = {
             let docs = self.document_map.read().await;
             docs.iter()
@@ -142,9 +730,496 @@ impl CssVariableLsp {
                 .collect::<Vec<_>>()
         };
 
+        let context = self.diagnostics_context().await;
         for (uri, text) in docs_snapshot {
-            self.validate_document_text(&uri, &text).await;
+            self.publish_document_diagnostics(&uri, &text, &context)
+                .await;
+        }
+    }
+
+    /// The configured tokens file and its current text, preferring the open
+    /// document over the file on disk.
+    async fn tokens_file_document(&self) -> Option<(Url, String)> {
+        let configured = PathBuf::from(self.manager.get_config().await.tokens_file?);
+        let path = if configured.is_absolute() {
+            configured
+        } else {
+            self.root_folder_path
+                .read()
+                .await
+                .as_ref()?
+                .join(configured)
+        };
+        let uri = Url::from_file_path(&path).ok()?;
+        let text = self.read_document_text(&uri).await?;
+        Some((uri, text))
+    }
+
+    /// Quick fixes creating the variable named by undefined-variable diagnostics.
+    async fn create_variable_actions(
+        &self,
+        uri: &Url,
+        text: &str,
+        diagnostics: &[Diagnostic],
+    ) -> Vec<CodeAction> {
+        let undefined_codes = [UNDEFINED_VARIABLE_CODE, UNDEFINED_VARIABLE_FALLBACK_CODE]
+            .map(|code| NumberOrString::String(code.to_string()));
+        let tokens_file = self.tokens_file_document().await;
+        let tokens_file_label = self.manager.get_config().await.tokens_file;
+        let is_html = is_html_like(&uri.path().to_lowercase());
+        let usages = self.manager.get_document_usages(uri).await;
+
+        let mut actions = Vec::new();
+        for diagnostic in diagnostics {
+            if !diagnostic
+                .code
+                .as_ref()
+                .is_some_and(|code| undefined_codes.contains(code))
+            {
+                continue;
+            }
+            let name = match diagnostic
+                .data
+                .as_ref()
+                .and_then(|data| data.get("name"))
+                .and_then(|name| name.as_str())
+            {
+                Some(name) => name,
+                None => continue,
+            };
+            let start = position_to_offset(text, diagnostic.range.start).unwrap_or(0);
+            // The usage's fallback, when it has one, makes a better value.
+            let value = usages
+                .iter()
+                .find(|usage| usage.range == diagnostic.range && usage.name == name)
+                .and_then(|usage| usage.fallback.as_ref())
+                .map_or(PLACEHOLDER_VALUE, |fallback| fallback.text.as_str());
+            let declaration = format!("{}: {};", name, value);
+
+            if let Some(edit) = root_insertion_edit(text, is_html, start, &declaration) {
+                actions.push(workspace_edit_action(
+                    format!("Create '{}' in :root", name),
+                    CodeActionKind::QUICKFIX,
+                    HashMap::from([(uri.clone(), vec![edit])]),
+                    Some(diagnostic),
+                ));
+            }
+            if let (Some((tokens_uri, tokens_text)), Some(label)) =
+                (&tokens_file, &tokens_file_label)
+            {
+                if tokens_uri == uri {
+                    continue;
+                }
+                let tokens_html = is_html_like(&tokens_uri.path().to_lowercase());
+                let edit =
+                    root_insertion_edit(tokens_text, tokens_html, tokens_text.len(), &declaration);
+                if let Some(edit) = edit {
+                    actions.push(workspace_edit_action(
+                        format!("Create '{}' in {}", name, label),
+                        CodeActionKind::QUICKFIX,
+                        HashMap::from([(tokens_uri.clone(), vec![edit])]),
+                        Some(diagnostic),
+                    ));
+                }
+            }
+        }
+        actions
+    }
+
+    /// Refactorings extracting a selected literal in a declaration value into
+    /// a new custom property.
+    async fn extract_variable_actions(
+        &self,
+        uri: &Url,
+        text: &str,
+        range: Range,
+    ) -> Vec<CodeAction> {
+        let (start, end) = match (
+            position_to_offset(text, range.start),
+            position_to_offset(text, range.end),
+        ) {
+            (Some(start), Some(end)) if start < end => (start, end),
+            _ => return Vec::new(),
+        };
+        let selection = &text[start..end];
+        let literal = selection.trim();
+        if literal.is_empty() || literal.starts_with("var(") {
+            return Vec::new();
+        }
+        let start = start + (selection.len() - selection.trim_start().len());
+        let end = start + literal.len();
+
+        let is_html = is_html_like(&uri.path().to_lowercase());
+        let value = match declaration_values(text, is_html)
+            .into_iter()
+            .find(|value| value.span.start <= start && end <= value.span.end)
+        {
+            Some(value) => value,
+            None => return Vec::new(),
+        };
+        let relative = start - value.span.start..end - value.span.start;
+        if !covers_whole_tokens(&text[value.span.clone()], relative) {
+            return Vec::new();
+        }
+
+        let taken: std::collections::HashSet<String> = self
+            .manager
+            .get_all_variables()
+            .await
+            .into_iter()
+            .map(|var| var.name)
+            .collect();
+        let name = suggest_variable_name(&value.property, |candidate| taken.contains(candidate));
+        let declaration = format!("{}: {};", name, literal);
+        let replacement = TextEdit {
+            range: Range::new(
+                crate::types::offset_to_position(text, start),
+                crate::types::offset_to_position(text, end),
+            ),
+            new_text: format!("var({})", name),
+        };
+
+        let mut actions = Vec::new();
+        if let Some(insert) = root_insertion_edit(text, is_html, start, &declaration) {
+            actions.push(workspace_edit_action(
+                format!("Extract to CSS variable '{}'", name),
+                CodeActionKind::REFACTOR_EXTRACT,
+                HashMap::from([(uri.clone(), vec![insert, replacement.clone()])]),
+                None,
+            ));
+        }
+
+        let tokens_file = self.tokens_file_document().await;
+        let tokens_file_label = self.manager.get_config().await.tokens_file;
+        if let (Some((tokens_uri, tokens_text)), Some(label)) = (&tokens_file, &tokens_file_label) {
+            let tokens_html = is_html_like(&tokens_uri.path().to_lowercase());
+            let insert =
+                root_insertion_edit(tokens_text, tokens_html, tokens_text.len(), &declaration);
+            if let (Some(insert), true) = (insert, tokens_uri != uri) {
+                actions.push(workspace_edit_action(
+                    format!("Extract to CSS variable '{}' in {}", name, label),
+                    CodeActionKind::REFACTOR_EXTRACT,
+                    HashMap::from([
+                        (tokens_uri.clone(), vec![insert]),
+                        (uri.clone(), vec![replacement]),
+                    ]),
+                    None,
+                ));
+            }
+        }
+
+        // The workspace-wide variant scans every file, so its edit is
+        // computed in codeAction/resolve.
+        actions.push(CodeAction {
+            title: format!(
+                "Extract to CSS variable '{}' and replace all occurrences",
+                name
+            ),
+            kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+            data: Some(serde_json::json!({
+                "action": EXTRACT_ALL_ACTION,
+                "uri": uri.as_str(),
+                "offset": start,
+                "literal": literal,
+                "name": name,
+            })),
+            ..Default::default()
+        });
+        actions
+    }
+
+    /// Refactorings replacing the `var()` call under the cursor with the
+    /// variable's cascade-winning value (or its fallback when undefined).
+    async fn inline_variable_actions(
+        &self,
+        uri: &Url,
+        text: &str,
+        position: Position,
+    ) -> Vec<CodeAction> {
+        let offset = match position_to_offset(text, position) {
+            Some(offset) => offset,
+            None => return Vec::new(),
+        };
+        let word = self.get_word_at_position(text, position);
+        let usages = match &word {
+            Some(word) => self.manager.get_usages(word).await,
+            None => return Vec::new(),
+        };
+        let usage = match usages.iter().find(|usage| {
+            let start = position_to_offset(text, usage.range.start).unwrap_or(0);
+            let end = position_to_offset(text, usage.range.end).unwrap_or(0);
+            &usage.uri == uri && start <= offset && offset <= end
+        }) {
+            Some(usage) => usage,
+            None => return Vec::new(),
+        };
+
+        let mut definitions = self.manager.get_variables(&usage.name).await;
+        let layer_order = self.manager.get_layer_order().await;
+        sort_by_cascade_with_layers(&mut definitions, &layer_order);
+        let value = match (definitions.first(), &usage.fallback) {
+            (Some(winner), _) => winner.value.clone(),
+            (None, Some(fallback)) => fallback.text.clone(),
+            (None, None) => return Vec::new(),
+        };
+
+        let mut actions = vec![workspace_edit_action(
+            format!("Inline CSS variable '{}'", usage.name),
+            CodeActionKind::REFACTOR_INLINE,
+            HashMap::from([(
+                uri.clone(),
+                vec![TextEdit {
+                    range: usage.range,
+                    new_text: value.clone(),
+                }],
+            )]),
+            None,
+        )];
+
+        // Inlining everywhere is only unambiguous with a single definition,
+        // which is then removed. References nested in other fallbacks would
+        // be left dangling, so the action is not offered when there are any.
+        let nested_references = self.manager.get_fallback_references(&usage.name).await;
+        if let ([definition], true) = (definitions.as_slice(), nested_references.is_empty()) {
+            let definition_text = match self.read_document_text(&definition.uri).await {
+                Some(text) => text,
+                None => return actions,
+            };
+            let start = position_to_offset(&definition_text, definition.range.start).unwrap_or(0);
+            let end = position_to_offset(&definition_text, definition.range.end).unwrap_or(start);
+            let removal = declaration_removal_range(&definition_text, start, end);
+
+            let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+            changes
+                .entry(definition.uri.clone())
+                .or_default()
+                .push(TextEdit {
+                    range: Range::new(
+                        crate::types::offset_to_position(&definition_text, removal.start),
+                        crate::types::offset_to_position(&definition_text, removal.end),
+                    ),
+                    new_text: String::new(),
+                });
+            for other in &usages {
+                let inside_definition = other.uri == definition.uri
+                    && other.range.start >= definition.range.start
+                    && other.range.end <= definition.range.end;
+                if inside_definition {
+                    continue;
+                }
+                changes
+                    .entry(other.uri.clone())
+                    .or_default()
+                    .push(TextEdit {
+                        range: other.range,
+                        new_text: value.clone(),
+                    });
+            }
+            actions.push(workspace_edit_action(
+                format!(
+                    "Inline all usages of '{}' and remove its definition",
+                    usage.name
+                ),
+                CodeActionKind::REFACTOR_INLINE,
+                changes,
+                None,
+            ));
+        }
+        actions
+    }
+
+    /// Text of a document, preferring the open document over the file on disk.
+    async fn read_document_text(&self, uri: &Url) -> Option<String> {
+        let open = {
+            let docs = self.document_map.read().await;
+            docs.get(uri).cloned()
+        };
+        match open {
+            Some(text) => Some(text),
+            None => tokio::fs::read_to_string(to_normalized_fs_path(uri)?)
+                .await
+                .ok(),
+        }
+    }
+
+    /// The custom property name whose stored name range in `uri` contains
+    /// `position` (a definition, `var()` usage or `@property` registration).
+    async fn name_at_position(&self, uri: &Url, position: Position) -> Option<(String, Range)> {
+        let contains = |range: &Range| range.start <= position && position <= range.end;
+        let definitions = self.manager.get_document_variables(uri).await;
+        let usages = self.manager.get_document_usages(uri).await;
+        let registrations = self.manager.get_document_registered_properties(uri).await;
+
+        definitions
+            .into_iter()
+            .filter_map(|def| Some((def.name, def.name_range?)))
+            .chain(
+                usages
+                    .into_iter()
+                    .filter_map(|usage| Some((usage.name, usage.name_range?))),
+            )
+            .chain(
+                registrations
+                    .into_iter()
+                    .map(|registration| (registration.name, registration.name_range)),
+            )
+            .find(|(_, range)| contains(range))
+    }
+
+    /// Every custom property name in a document (definitions, `var()` usages
+    /// and `@property` registrations) with its semantic token modifiers.
+    async fn classify_document_names(&self, uri: &Url) -> Vec<ClassifiedName> {
+        let document_modifiers = if is_third_party(uri) {
+            semantic_tokens::DEFAULT_LIBRARY
+        } else {
+            0
+        };
+        let mut names = Vec::new();
+
+        for definition in self.manager.get_document_variables(uri).await {
+            let Some(range) = definition.name_range else {
+                continue;
+            };
+            let mut modifiers = semantic_tokens::DECLARATION | document_modifiers;
+            if definition.deprecated {
+                modifiers |= semantic_tokens::DEPRECATED;
+            }
+            if !self
+                .manager
+                .get_registered_properties(&definition.name)
+                .await
+                .is_empty()
+            {
+                modifiers |= semantic_tokens::READONLY;
+            }
+            names.push(ClassifiedName { range, modifiers });
+        }
+
+        for registration in self.manager.get_document_registered_properties(uri).await {
+            names.push(ClassifiedName {
+                range: registration.name_range,
+                modifiers: semantic_tokens::DECLARATION
+                    | semantic_tokens::READONLY
+                    | document_modifiers,
+            });
+        }
+
+        for usage in self.manager.get_document_usages(uri).await {
+            let Some(range) = usage.name_range else {
+                continue;
+            };
+            let mut modifiers = 0;
+            match self.manager.get_winning_definition(&usage.name).await {
+                Some(winner) => {
+                    if winner.deprecated {
+                        modifiers |= semantic_tokens::DEPRECATED;
+                    }
+                    if is_third_party(&winner.uri) {
+                        modifiers |= semantic_tokens::DEFAULT_LIBRARY;
+                    }
+                }
+                None => modifiers |= semantic_tokens::UNDEFINED,
+            }
+            if !self
+                .manager
+                .get_registered_properties(&usage.name)
+                .await
+                .is_empty()
+            {
+                modifiers |= semantic_tokens::READONLY;
+            }
+            names.push(ClassifiedName { range, modifiers });
+        }
+
+        names
+    }
+
+    /// CSS and HTML files in the workspace folders
+    async fn workspace_file_uris(&self) -> Vec<Url> {
+        let config = self.manager.get_config().await;
+        let mut folders = self.workspace_folder_paths.read().await.clone();
+        if folders.is_empty() {
+            folders.extend(self.root_folder_path.read().await.clone());
+        }
+        collect_workspace_files(&folders, &config)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|path| Url::from_file_path(path).ok())
+            .collect()
+    }
+
+    /// Text of every open document and workspace file, preferring the open
+    /// document over the file on disk.
+    async fn workspace_documents(&self) -> HashMap<Url, String> {
+        let mut documents: HashMap<Url, String> = self.document_map.read().await.clone();
+        for file_uri in self.workspace_file_uris().await {
+            if documents.contains_key(&file_uri) {
+                continue;
+            }
+            let Some(path) = to_normalized_fs_path(&file_uri) else {
+                continue;
+            };
+            if let Ok(text) = tokio::fs::read_to_string(&path).await {
+                documents.insert(file_uri, text);
+            }
+        }
+        documents
+    }
+
+    /// Edit creating `name` with the value `literal` (in the tokens file when
+    /// configured, otherwise near `offset` in `uri`) and replacing every
+    /// occurrence of the literal in open documents and workspace files.
+    async fn extract_all_edit(
+        &self,
+        uri: &Url,
+        offset: usize,
+        literal: &str,
+        name: &str,
+    ) -> Option<WorkspaceEdit> {
+        let mut documents = self.workspace_documents().await;
+
+        let declaration = format!("{}: {};", name, literal);
+        let (target_uri, insert) = match self.tokens_file_document().await {
+            Some((tokens_uri, tokens_text)) => {
+                let tokens_html = is_html_like(&tokens_uri.path().to_lowercase());
+                let insert =
+                    root_insertion_edit(&tokens_text, tokens_html, tokens_text.len(), &declaration);
+                documents.entry(tokens_uri.clone()).or_insert(tokens_text);
+                (tokens_uri, insert?)
+            }
+            None => {
+                let text = documents.get(uri)?;
+                let is_html = is_html_like(&uri.path().to_lowercase());
+                (
+                    uri.clone(),
+                    root_insertion_edit(text, is_html, offset, &declaration)?,
+                )
+            }
+        };
+
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        changes.entry(target_uri).or_default().push(insert);
+        for (doc_uri, text) in &documents {
+            let path = doc_uri.path().to_lowercase();
+            if !is_css_like(&path) && !is_html_like(&path) {
+                continue;
+            }
+            for occurrence in find_literal_occurrences(text, is_html_like(&path), literal) {
+                changes.entry(doc_uri.clone()).or_default().push(TextEdit {
+                    range: Range::new(
+                        crate::types::offset_to_position(text, occurrence.start),
+                        crate::types::offset_to_position(text, occurrence.end),
+                    ),
+                    new_text: format!("var({})", name),
+                });
+            }
         }
+
+        Some(WorkspaceEdit {
+            changes: Some(changes),
+            document_changes: None,
+            change_annotations: None,
+        })
     }
 
     async fn update_document_from_disk(&self, uri: &Url) {
@@ -271,6 +1346,46 @@ impl LanguageServer for CssVariableLsp {
             .and_then(|t| t.publish_diagnostics.as_ref())
             .and_then(|p| p.related_information)
             .unwrap_or(false);
+        let has_link_support = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|t| t.definition.as_ref())
+            .and_then(|d| d.link_support)
+            .unwrap_or(false);
+        let has_code_lens_refresh = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|w| w.code_lens.as_ref())
+            .and_then(|c| c.refresh_support)
+            .unwrap_or(false);
+        let has_declaration_link_support = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|t| t.declaration.as_ref())
+            .and_then(|d| d.link_support)
+            .unwrap_or(false);
+        let has_pull_diagnostics = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .is_some_and(|t| t.diagnostic.is_some());
+        let has_diagnostic_refresh = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|w| w.diagnostic.as_ref())
+            .and_then(|d| d.refresh_support)
+            .unwrap_or(false);
+        let has_symbol_location_resolve = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|w| w.symbol.as_ref())
+            .and_then(|s| s.resolve_support.as_ref())
+            .is_some_and(|r| r.properties.iter().any(|p| p == "location.range"));
 
         {
             let mut cap = self.has_workspace_folder_capability.write().await;
@@ -280,6 +1395,30 @@ impl LanguageServer for CssVariableLsp {
             let mut rel = self.has_diagnostic_related_information.write().await;
             *rel = has_related_info;
         }
+        {
+            let mut links = self.has_definition_link_support.write().await;
+            *links = has_link_support;
+        }
+        {
+            let mut links = self.has_declaration_link_support.write().await;
+            *links = has_declaration_link_support;
+        }
+        {
+            let mut refresh = self.has_code_lens_refresh_support.write().await;
+            *refresh = has_code_lens_refresh;
+        }
+        {
+            let mut pull = self.has_pull_diagnostics_support.write().await;
+            *pull = has_pull_diagnostics;
+        }
+        {
+            let mut refresh = self.has_diagnostic_refresh_support.write().await;
+            *refresh = has_diagnostic_refresh;
+        }
+        {
+            let mut resolve = self.has_symbol_location_resolve_support.write().await;
+            *resolve = has_symbol_location_resolve;
+        }
 
         if let Some(root_uri) = params.root_uri.as_ref() {
             let root_path = to_normalized_fs_path(root_uri);
@@ -309,10 +1448,50 @@ impl LanguageServer for CssVariableLsp {
             }),
             hover_provider: Some(tower_lsp::lsp_types::HoverProviderCapability::Simple(true)),
             definition_provider: Some(OneOf::Left(true)),
+            declaration_provider: Some(DeclarationCapability::Simple(true)),
             references_provider: Some(OneOf::Left(true)),
-            rename_provider: Some(OneOf::Left(true)),
+            document_highlight_provider: Some(OneOf::Left(true)),
+            code_lens_provider: Some(CodeLensOptions {
+                resolve_provider: Some(false),
+            }),
+            execute_command_provider: Some(ExecuteCommandOptions {
+                commands: vec![SHOW_REFERENCES_COMMAND.to_string()],
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            }),
+            rename_provider: Some(OneOf::Right(RenameOptions {
+                prepare_provider: Some(true),
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            })),
             document_symbol_provider: Some(OneOf::Left(true)),
-            workspace_symbol_provider: Some(OneOf::Left(true)),
+            code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                code_action_kinds: Some(vec![
+                    CodeActionKind::QUICKFIX,
+                    CodeActionKind::REFACTOR_EXTRACT,
+                    CodeActionKind::REFACTOR_INLINE,
+                ]),
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+                resolve_provider: Some(true),
+            })),
+            workspace_symbol_provider: Some(OneOf::Right(WorkspaceSymbolOptions {
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+                resolve_provider: Some(true),
+            })),
+            diagnostic_provider: Some(DiagnosticServerCapabilities::Options(DiagnosticOptions {
+                identifier: Some("css-variable-lsp".to_string()),
+                inter_file_dependencies: true,
+                workspace_diagnostics: true,
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            })),
+            inlay_hint_provider: Some(OneOf::Left(true)),
+            semantic_tokens_provider: Some(
+                SemanticTokensOptions {
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                    legend: semantic_tokens::legend(),
+                    range: Some(true),
+                    full: Some(SemanticTokensFullOptions::Bool(true)),
+                }
+                .into(),
+            ),
             color_provider: if self.runtime_config.enable_color_provider {
                 Some(ColorProviderCapability::Simple(true))
             } else {
@@ -363,8 +1542,10 @@ impl LanguageServer for CssVariableLsp {
             let mut docs = self.document_map.write().await;
             docs.insert(uri.clone(), text.clone());
         }
+        let fingerprint = self.manager.document_fingerprint(&uri).await;
         self.parse_document_text(&uri, &text).await;
         self.validate_document_text(&uri, &text).await;
+        self.propagate_document_update(&uri, fingerprint).await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -374,8 +1555,10 @@ impl LanguageServer for CssVariableLsp {
             Some(text) => text,
             None => return,
         };
+        let fingerprint = self.manager.document_fingerprint(&uri).await;
         self.parse_document_text(&uri, &updated_text).await;
         self.validate_document_text(&uri, &updated_text).await;
+        self.propagate_document_update(&uri, fingerprint).await;
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
@@ -384,7 +1567,9 @@ impl LanguageServer for CssVariableLsp {
             let mut docs = self.document_map.write().await;
             docs.remove(&uri);
         }
+        let fingerprint = self.manager.document_fingerprint(&uri).await;
         self.update_document_from_disk(&uri).await;
+        self.propagate_document_update(&uri, fingerprint).await;
     }
 
     async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
@@ -393,16 +1578,17 @@ impl LanguageServer for CssVariableLsp {
                 FileChangeType::DELETED => {
                     self.manager.remove_document(&change.uri).await;
                 }
-                FileChangeType::CREATED | FileChangeType::CHANGED => {
-                    if !self.is_document_open(&change.uri).await {
-                        self.update_document_from_disk(&change.uri).await;
-                    }
+                FileChangeType::CREATED | FileChangeType::CHANGED
+                    if !self.is_document_open(&change.uri).await =>
+                {
+                    self.update_document_from_disk(&change.uri).await;
                 }
                 _ => {}
             }
         }
 
         self.validate_all_open_documents().await;
+        self.refresh_code_lenses().await;
     }
 
     async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
@@ -452,25 +1638,28 @@ impl LanguageServer for CssVariableLsp {
         let property_name = self.get_property_name_from_context(&text, position);
         let variables = self.manager.get_all_variables().await;
 
-        let mut unique_vars = HashMap::new();
+        let layer_order = self.manager.get_layer_order().await;
+        let mut grouped_vars: HashMap<String, Vec<CssVariable>> = HashMap::new();
         for var in variables {
-            unique_vars.entry(var.name.clone()).or_insert(var);
+            grouped_vars.entry(var.name.clone()).or_default().push(var);
         }
 
-        let mut scored_vars: Vec<(i32, _)> = unique_vars
-            .values()
-            .map(|var| {
-                let score = score_variable_relevance(&var.name, property_name.as_deref());
-                (score, var)
+        let mut scored_vars: Vec<(i32, &Vec<CssVariable>)> = grouped_vars
+            .values_mut()
+            .map(|definitions| {
+                sort_by_cascade_with_layers(definitions, &layer_order);
+                let score =
+                    score_variable_relevance(&definitions[0].name, property_name.as_deref());
+                (score, &*definitions)
             })
             .collect();
 
         scored_vars.retain(|(score, _)| *score != 0);
-        scored_vars.sort_by(|(score_a, var_a), (score_b, var_b)| {
+        scored_vars.sort_by(|(score_a, defs_a), (score_b, defs_b)| {
             if score_a != score_b {
                 return score_b.cmp(score_a);
             }
-            var_a.name.cmp(&var_b.name)
+            defs_a[0].name.cmp(&defs_b[0].name)
         });
 
         let workspace_folder_paths = self.workspace_folder_paths.read().await.clone();
@@ -478,7 +1667,8 @@ impl LanguageServer for CssVariableLsp {
 
         let items = scored_vars
             .into_iter()
-            .map(|(_, var)| {
+            .map(|(_, definitions)| {
+                let var = &definitions[0];
                 let options = PathDisplayOptions {
                     mode: self.runtime_config.path_display_mode,
                     abbrev_length: self.runtime_config.path_display_abbrev_length,
@@ -488,7 +1678,7 @@ impl LanguageServer for CssVariableLsp {
                 CompletionItem {
                     label: var.name.clone(),
                     kind: Some(CompletionItemKind::VARIABLE),
-                    detail: Some(var.value.clone()),
+                    detail: Some(format_completion_detail(definitions)),
                     documentation: Some(tower_lsp::lsp_types::Documentation::String(format!(
                         "Defined in {}",
                         format_uri_for_display(&var.uri, options)
@@ -519,11 +1709,6 @@ impl LanguageServer for CssVariableLsp {
             None => return Ok(None),
         };
 
-        let mut definitions = self.manager.get_variables(&word).await;
-        if definitions.is_empty() {
-            return Ok(None);
-        }
-
         let usages = self.manager.get_usages(&word).await;
         let offset = match position_to_offset(&text, position) {
             Some(offset) => offset,
@@ -537,6 +1722,30 @@ impl LanguageServer for CssVariableLsp {
             let end = position_to_offset(&text, usage.range.end).unwrap_or(0);
             offset >= start && offset <= end
         });
+        let fallback = hover_usage.and_then(|u| u.fallback.as_ref());
+
+        let mut definitions = self.manager.get_variables(&word).await;
+        let registrations = self.manager.get_registered_properties(&word).await;
+        if definitions.is_empty() && registrations.is_empty() {
+            let (Some(usage), Some(fallback)) = (hover_usage, fallback) else {
+                return Ok(None);
+            };
+            let mut hover_text = format!(
+                "### CSS Variable: `{}`\n\n**Not defined** in the workspace\n\n",
+                word
+            );
+            hover_text.push_str(&format_fallback(&fallback.text));
+            if let Some(resolved) = self.manager.resolve_usage(usage).await {
+                hover_text.push_str(&format_resolution(&resolved, &fallback.text));
+            }
+            return Ok(Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: hover_text,
+                }),
+                range: None,
+            }));
+        }
 
         let usage_context = hover_usage
             .map(|u| u.usage_context.clone())
@@ -545,8 +1754,10 @@ impl LanguageServer for CssVariableLsp {
         let dom_tree = self.manager.get_dom_tree(&uri).await;
         let dom_node = hover_usage.and_then(|u| u.dom_node.clone());
 
-        sort_by_cascade(&mut definitions);
+        let layer_order = self.manager.get_layer_order().await;
+        sort_by_cascade_with_layers(&mut definitions, &layer_order);
 
+        let resolved = self.manager.resolve_variable(&word).await;
         let mut hover_text = format!("### CSS Variable: `{}`\n\n", word);
 
         if definitions.len() == 1 {
@@ -563,7 +1774,16 @@ impl LanguageServer for CssVariableLsp {
                     format_specificity(calculate_specificity(&var.selector))
                 ));
             }
-        } else {
+            if let Some(condition) = format_conditions(&var.conditions) {
+                hover_text.push_str(&format!("**Condition:** {}\n", condition));
+            }
+            if let Some(layer) = layer_order.layer_name(var) {
+                hover_text.push_str(&format!("**Layer:** `{}`\n", display_layer_name(&layer)));
+            }
+            if let Some(resolved) = &resolved {
+                hover_text.push_str(&format_resolution(resolved, &var.value));
+            }
+        } else if !definitions.is_empty() {
             hover_text.push_str("**Definitions** (CSS cascade order):\n\n");
 
             for (idx, var) in definitions.iter().enumerate() {
@@ -591,6 +1811,12 @@ impl LanguageServer for CssVariableLsp {
                         format_specificity(spec)
                     ));
                 }
+                if let Some(condition) = format_conditions(&var.conditions) {
+                    line.push_str(&format!(" when _{}_", condition));
+                }
+                if let Some(layer) = layer_order.layer_name(var) {
+                    line.push_str(&format!(" in layer `{}`", display_layer_name(&layer)));
+                }
 
                 if is_winner && !usage_context.is_empty() {
                     if var.important {
@@ -608,6 +1834,10 @@ impl LanguageServer for CssVariableLsp {
                     let winner = &definitions[0];
                     if winner.important && !var.important {
                         line.push_str(" _(overridden by !important)_");
+                    } else if is_conditional(var) && !is_conditional(winner) {
+                        line.push_str(" _(conditional)_");
+                    } else if layer_order.compare(winner, var) == std::cmp::Ordering::Less {
+                        line.push_str(" _(lower-priority layer)_");
                     } else {
                         let winner_spec = calculate_specificity(&winner.selector);
                         let cmp = compare_specificity(winner_spec, spec);
@@ -635,6 +1865,25 @@ impl LanguageServer for CssVariableLsp {
                     hover_text.push_str(&format!("\n_Context: `{}`_", usage_context));
                 }
             }
+            if let Some(resolved) = &resolved {
+                let resolution = format_resolution(resolved, &definitions[0].value);
+                if !resolution.is_empty() {
+                    hover_text.push('\n');
+                    hover_text.push_str(&resolution);
+                }
+            }
+        }
+
+        if let Some(fallback) = fallback.filter(|_| !definitions.is_empty()) {
+            hover_text.push('\n');
+            hover_text.push_str(&format_fallback(&fallback.text));
+        }
+
+        if let Some(registration) = registrations.first() {
+            if !definitions.is_empty() {
+                hover_text.push('\n');
+            }
+            hover_text.push_str(&format_registration(registration));
         }
 
         Ok(Some(Hover {
@@ -667,16 +1916,160 @@ impl LanguageServer for CssVariableLsp {
             None => return Ok(None),
         };
 
-        let definitions = self.manager.get_variables(&word).await;
-        let first = match definitions.first() {
-            Some(def) => def,
+        let usages = self.manager.get_usages(&word).await;
+        let usage = usages.iter().find(|usage| {
+            usage.uri == uri && usage.range.start <= position && position <= usage.range.end
+        });
+        let origin_selection_range = self
+            .name_at_position(&uri, position)
+            .await
+            .map(|(_, range)| range);
+
+        let mut definitions = self.manager.get_variables(&word).await;
+        if let Some(usage) = usage {
+            let dom_tree = self.manager.get_dom_tree(&uri).await;
+            definitions = filter_by_dom_match(definitions, usage, dom_tree.as_ref());
+        }
+        let layer_order = self.manager.get_layer_order().await;
+        sort_by_cascade_with_layers(&mut definitions, &layer_order);
+
+        let links: Vec<LocationLink> = definitions
+            .iter()
+            .map(|def| definition_link(def, origin_selection_range))
+            .collect();
+
+        let link_support = *self.has_definition_link_support.read().await;
+        Ok(definition_response(links, link_support))
+    }
+
+    async fn goto_declaration(
+        &self,
+        params: GotoDeclarationParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<GotoDeclarationResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        let text = {
+            let docs = self.document_map.read().await;
+            docs.get(&uri).cloned()
+        };
+        let text = match text {
+            Some(text) => text,
             None => return Ok(None),
         };
 
-        Ok(Some(GotoDefinitionResponse::Scalar(Location::new(
-            first.uri.clone(),
-            first.range,
-        ))))
+        let word = match self.get_word_at_position(&text, position) {
+            Some(word) => word,
+            None => return Ok(None),
+        };
+        let origin_selection_range = self
+            .name_at_position(&uri, position)
+            .await
+            .map(|(_, range)| range);
+
+        let registrations = self.manager.get_registered_properties(&word).await;
+        let links: Vec<LocationLink> = if registrations.is_empty() {
+            // Without `@property`, the `:root` assignment acts as the
+            // declaration, or else the definition winning the cascade.
+            let mut definitions: Vec<CssVariable> = self
+                .manager
+                .get_variables(&word)
+                .await
+                .into_iter()
+                .filter(|def| def.selector.trim() == ":root")
+                .collect();
+            if definitions.is_empty() {
+                definitions.extend(self.manager.get_winning_definition(&word).await);
+            }
+            let layer_order = self.manager.get_layer_order().await;
+            sort_by_cascade_with_layers(&mut definitions, &layer_order);
+            definitions
+                .iter()
+                .map(|def| definition_link(def, origin_selection_range))
+                .collect()
+        } else {
+            registrations
+                .iter()
+                .map(|registration| LocationLink {
+                    origin_selection_range,
+                    target_uri: registration.uri.clone(),
+                    target_range: registration.range,
+                    target_selection_range: registration.name_range,
+                })
+                .collect()
+        };
+
+        let link_support = *self.has_declaration_link_support.read().await;
+        Ok(definition_response(links, link_support))
+    }
+
+    async fn code_action(
+        &self,
+        params: CodeActionParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let text = {
+            let docs = self.document_map.read().await;
+            docs.get(&uri).cloned()
+        };
+        let text = match text {
+            Some(text) => text,
+            None => return Ok(None),
+        };
+
+        let mut actions = self
+            .create_variable_actions(&uri, &text, &params.context.diagnostics)
+            .await;
+        actions.extend(replace_with_variable_actions(
+            &uri,
+            &params.context.diagnostics,
+        ));
+        actions.extend(
+            self.extract_variable_actions(&uri, &text, params.range)
+                .await,
+        );
+        actions.extend(
+            self.inline_variable_actions(&uri, &text, params.range.start)
+                .await,
+        );
+
+        if actions.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(
+                actions
+                    .into_iter()
+                    .map(CodeActionOrCommand::CodeAction)
+                    .collect(),
+            ))
+        }
+    }
+
+    async fn code_action_resolve(
+        &self,
+        mut action: CodeAction,
+    ) -> tower_lsp::jsonrpc::Result<CodeAction> {
+        let data = match &action.data {
+            Some(data)
+                if data.get("action").and_then(|a| a.as_str()) == Some(EXTRACT_ALL_ACTION) =>
+            {
+                data.clone()
+            }
+            _ => return Ok(action),
+        };
+        let field = |key: &str| data.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        let (uri, literal, name) = match (field("uri"), field("literal"), field("name")) {
+            (Some(uri), Some(literal), Some(name)) => (uri, literal, name),
+            _ => return Ok(action),
+        };
+        let uri = match Url::parse(&uri) {
+            Ok(uri) => uri,
+            Err(_) => return Ok(action),
+        };
+        let offset = data.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+
+        action.edit = self.extract_all_edit(&uri, offset, &literal, &name).await;
+        Ok(action)
     }
 
     async fn references(
@@ -712,6 +2105,139 @@ impl LanguageServer for CssVariableLsp {
         Ok(Some(locations))
     }
 
+    async fn code_lens(
+        &self,
+        params: CodeLensParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<Vec<CodeLens>>> {
+        let uri = params.text_document.uri;
+        let mut lenses = Vec::new();
+
+        // One lens per count rather than a combined "12 usages · 3 overrides"
+        // title, since each lens runs a single command and the two counts
+        // open differently filtered views
+        for def in self.manager.get_document_variables(&uri).await {
+            let (definitions, usages) = self.manager.get_references(&def.name).await;
+            let range = def.name_range.unwrap_or(def.range);
+            let overrides = definitions
+                .iter()
+                .filter(|other| !is_same_definition(other, &def.uri, range.start))
+                .count();
+
+            lenses.push(CodeLens {
+                range,
+                command: Some(show_references_command(
+                    pluralize(usages.len(), "usage"),
+                    &def.name,
+                    &uri,
+                    range.start,
+                    "usages",
+                )),
+                data: None,
+            });
+            if overrides > 0 {
+                lenses.push(CodeLens {
+                    range,
+                    command: Some(show_references_command(
+                        pluralize(overrides, "override"),
+                        &def.name,
+                        &uri,
+                        range.start,
+                        "overrides",
+                    )),
+                    data: None,
+                });
+            }
+        }
+
+        Ok(Some(lenses))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<serde_json::Value>> {
+        if params.command != SHOW_REFERENCES_COMMAND {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                "Unknown command '{}'",
+                params.command
+            )));
+        }
+        let arguments: ShowReferencesArguments = params
+            .arguments
+            .into_iter()
+            .next()
+            .and_then(|argument| serde_json::from_value(argument).ok())
+            .ok_or_else(|| {
+                tower_lsp::jsonrpc::Error::invalid_params("Expected name, uri, position and filter")
+            })?;
+
+        let (definitions, usages) = self.manager.get_references(&arguments.name).await;
+        let locations: Vec<Location> = match arguments.filter.as_str() {
+            "usages" => usages
+                .into_iter()
+                .map(|usage| Location::new(usage.uri, usage.range))
+                .collect(),
+            // Every definition except the one the lens is on
+            "overrides" => definitions
+                .into_iter()
+                .filter(|def| !is_same_definition(def, &arguments.uri, arguments.position))
+                .map(|def| Location::new(def.uri, def.range))
+                .collect(),
+            _ => {
+                return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                    "Expected a filter of 'usages' or 'overrides'",
+                ))
+            }
+        };
+
+        Ok(Some(serde_json::json!({
+            "uri": arguments.uri,
+            "position": arguments.position,
+            "locations": locations,
+        })))
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<Vec<DocumentHighlight>>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        let name = match self.name_at_position(&uri, position).await {
+            Some((name, _)) => name,
+            None => return Ok(None),
+        };
+
+        let (definitions, usages, registrations) =
+            self.manager.get_references_unescaped(&name).await;
+        let mut highlights = Vec::new();
+        for def in definitions.into_iter().filter(|def| def.uri == uri) {
+            highlights.push(DocumentHighlight {
+                range: def.name_range.unwrap_or(def.range),
+                kind: Some(DocumentHighlightKind::WRITE),
+            });
+        }
+        for usage in usages.into_iter().filter(|usage| usage.uri == uri) {
+            highlights.push(DocumentHighlight {
+                range: usage.name_range.unwrap_or(usage.range),
+                kind: Some(DocumentHighlightKind::READ),
+            });
+        }
+        for registration in registrations
+            .into_iter()
+            .filter(|registration| registration.uri == uri)
+        {
+            highlights.push(DocumentHighlight {
+                range: registration.name_range,
+                kind: Some(DocumentHighlightKind::TEXT),
+            });
+        }
+        highlights.sort_by_key(|highlight| highlight.range.start);
+
+        Ok(Some(highlights))
+    }
+
     async fn document_color(
         &self,
         params: DocumentColorParams,
@@ -749,15 +2275,12 @@ impl LanguageServer for CssVariableLsp {
             }
         }
 
-        for caps in self.var_usage_regex.captures_iter(&text) {
-            let match_all = caps.get(0).unwrap();
-            let var_name = caps.get(1).unwrap().as_str();
-            if let Some(color) = self.manager.resolve_variable_color(var_name).await {
-                let range = Range::new(
-                    crate::types::offset_to_position(&text, match_all.start()),
-                    crate::types::offset_to_position(&text, match_all.end()),
-                );
-                colors.push(ColorInformation { range, color });
+        for usage in self.manager.get_document_usages(&uri).await {
+            if let Some(color) = self.manager.resolve_usage_color(&usage).await {
+                colors.push(ColorInformation {
+                    range: usage.range,
+                    color,
+                });
             }
         }
 
@@ -774,29 +2297,140 @@ impl LanguageServer for CssVariableLsp {
         Ok(generate_color_presentations(params.color, params.range))
     }
 
+    async fn inlay_hint(
+        &self,
+        params: InlayHintParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<Vec<InlayHint>>> {
+        let uri = params.text_document.uri;
+        let visible = params.range;
+        let config = self.manager.get_config().await;
+
+        let usages = self.manager.get_document_usages(&uri).await;
+        let mut visible_usages: Vec<&CssVariableUsage> = usages
+            .iter()
+            .filter(|usage| usage.range.end >= visible.start && usage.range.start <= visible.end)
+            .collect();
+        visible_usages.sort_by_key(|usage| usage.range.start);
+
+        let workspace_folder_paths = self.workspace_folder_paths.read().await.clone();
+        let root_folder_path = self.root_folder_path.read().await.clone();
+
+        let mut hints = Vec::new();
+        for usage in visible_usages {
+            let Some(resolved) = self.manager.resolve_variable(&usage.name).await else {
+                continue;
+            };
+            let Some(winner) = self.manager.get_winning_definition(&usage.name).await else {
+                continue;
+            };
+            if config.inlay_hints_non_color_only && parse_color(&resolved.value).is_some() {
+                continue;
+            }
+            let value = evaluate_math(&resolved.value).unwrap_or(resolved.value);
+            let options = PathDisplayOptions {
+                mode: self.runtime_config.path_display_mode,
+                abbrev_length: self.runtime_config.path_display_abbrev_length,
+                workspace_folder_paths: &workspace_folder_paths,
+                root_folder_path: root_folder_path.as_ref(),
+            };
+            let mut tooltip = format!(
+                "`{}` defined in {}",
+                usage.name,
+                format_uri_for_display(&winner.uri, options)
+            );
+            if !winner.selector.is_empty() {
+                tooltip.push_str(&format!(" (`{}`)", winner.selector));
+            }
+            hints.push(InlayHint {
+                position: usage.range.end,
+                label: InlayHintLabel::String(format_inlay_hint_label(
+                    &value,
+                    config.inlay_hint_max_length,
+                )),
+                kind: None,
+                text_edits: None,
+                tooltip: Some(InlayHintTooltip::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: tooltip,
+                })),
+                padding_left: None,
+                padding_right: None,
+                data: None,
+            });
+        }
+
+        Ok(Some(hints))
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<PrepareRenameResponse>> {
+        Ok(self
+            .name_at_position(&params.text_document.uri, params.position)
+            .await
+            .map(
+                |(name, range)| PrepareRenameResponse::RangeWithPlaceholder {
+                    range,
+                    placeholder: name,
+                },
+            ))
+    }
+
     async fn rename(
         &self,
         params: RenameParams,
     ) -> tower_lsp::jsonrpc::Result<Option<WorkspaceEdit>> {
         let uri = params.text_document_position.text_document.uri;
         let position = params.text_document_position.position;
-        let new_name = params.new_name;
+        let new_name = params.new_name.trim().to_string();
 
-        let text = {
-            let docs = self.document_map.read().await;
-            docs.get(&uri).cloned()
-        };
-        let text = match text {
-            Some(text) => text,
-            None => return Ok(None),
+        let old_name = match self.name_at_position(&uri, position).await {
+            Some((name, _)) => name,
+            None => {
+                let text = {
+                    let docs = self.document_map.read().await;
+                    docs.get(&uri).cloned()
+                };
+                match text.and_then(|text| self.get_word_at_position(&text, position)) {
+                    Some(word) => word,
+                    None => return Ok(None),
+                }
+            }
         };
 
-        let old_name = match self.get_word_at_position(&text, position) {
-            Some(word) => word,
-            None => return Ok(None),
-        };
+        validate_custom_property_name(&new_name)
+            .map_err(tower_lsp::jsonrpc::Error::invalid_params)?;
+        let new_key = unescape_identifier(&new_name);
+        if new_key == unescape_identifier(&old_name) {
+            return Ok(None);
+        }
+
+        let collides = self
+            .manager
+            .get_all_variables()
+            .await
+            .iter()
+            .any(|var| unescape_identifier(&var.name) == new_key);
+        if collides {
+            let message = format!("'{}' is already defined", new_name);
+            match self.manager.get_config().await.rename_collision {
+                RenameCollision::Refuse => {
+                    return Err(tower_lsp::jsonrpc::Error::invalid_params(message));
+                }
+                RenameCollision::Warn => {
+                    self.client
+                        .show_message(
+                            MessageType::WARNING,
+                            format!("{}; '{}' now shares its definitions", message, old_name),
+                        )
+                        .await;
+                }
+            }
+        }
 
-        let (definitions, usages) = self.manager.get_references(&old_name).await;
+        let (definitions, usages, registrations) =
+            self.manager.get_references_unescaped(&old_name).await;
         let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
 
         for def in definitions {
@@ -818,6 +2452,16 @@ impl LanguageServer for CssVariableLsp {
                 });
         }
 
+        for registration in registrations {
+            changes
+                .entry(registration.uri.clone())
+                .or_default()
+                .push(TextEdit {
+                    range: registration.name_range,
+                    new_text: new_name.clone(),
+                });
+        }
+
         Ok(Some(WorkspaceEdit {
             changes: Some(changes),
             document_changes: None,
@@ -825,30 +2469,46 @@ impl LanguageServer for CssVariableLsp {
         }))
     }
 
-    #[allow(deprecated)]
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<SemanticTokensResult>> {
+        let names = self
+            .classify_document_names(&params.text_document.uri)
+            .await;
+        Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic_tokens::encode(names, None),
+        })))
+    }
+
+    async fn semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> tower_lsp::jsonrpc::Result<Option<SemanticTokensRangeResult>> {
+        let names = self
+            .classify_document_names(&params.text_document.uri)
+            .await;
+        Ok(Some(SemanticTokensRangeResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic_tokens::encode(names, Some(params.range)),
+        })))
+    }
+
     async fn document_symbol(
         &self,
         params: DocumentSymbolParams,
     ) -> tower_lsp::jsonrpc::Result<Option<DocumentSymbolResponse>> {
-        let vars = self
-            .manager
-            .get_document_variables(&params.text_document.uri)
-            .await;
-        let symbols: Vec<DocumentSymbol> = vars
-            .into_iter()
-            .map(|var| DocumentSymbol {
-                name: var.name,
-                detail: Some(var.value),
-                kind: SymbolKind::VARIABLE,
-                tags: None,
-                deprecated: None,
-                range: var.range,
-                selection_range: var.range,
-                children: None,
-            })
-            .collect();
+        let uri = params.text_document.uri;
+        let text = match self.read_document_text(&uri).await {
+            Some(text) => text,
+            None => return Ok(None),
+        };
+        let is_html = is_html_like(&uri.path().to_lowercase());
 
-        Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+        Ok(Some(DocumentSymbolResponse::Nested(document_outline(
+            &text, is_html,
+        ))))
     }
 
     #[allow(deprecated)]
@@ -856,26 +2516,143 @@ impl LanguageServer for CssVariableLsp {
         &self,
         params: WorkspaceSymbolParams,
     ) -> tower_lsp::jsonrpc::Result<Option<Vec<SymbolInformation>>> {
-        let query = params.query.to_lowercase();
-        let vars = self.manager.get_all_variables().await;
-        let mut symbols = Vec::new();
-
-        for var in vars {
-            if !query.is_empty() && !var.name.to_lowercase().contains(&query) {
-                continue;
-            }
-            symbols.push(SymbolInformation {
+        let symbols = self
+            .ranked_symbols(&params.query)
+            .await
+            .into_iter()
+            .map(|(var, container_name)| SymbolInformation {
                 name: var.name,
                 kind: SymbolKind::VARIABLE,
                 tags: None,
                 deprecated: None,
                 location: Location::new(var.uri, var.range),
-                container_name: None,
-            });
-        }
+                container_name: Some(container_name),
+            })
+            .collect();
 
         Ok(Some(symbols))
     }
+
+    /// Fill in the range of a symbol returned by `workspace_symbols` with
+    /// only its document. The definition is picked by name and by the
+    /// selector kept in the symbol's data.
+    async fn symbol_resolve(
+        &self,
+        params: WorkspaceSymbol,
+    ) -> tower_lsp::jsonrpc::Result<WorkspaceSymbol> {
+        let OneOf::Right(WorkspaceLocation { uri }) = &params.location else {
+            return Ok(params);
+        };
+        let selector = params
+            .data
+            .as_ref()
+            .and_then(|data| data.get("selector"))
+            .and_then(|selector| selector.as_str());
+        let definitions: Vec<CssVariable> = self
+            .manager
+            .get_variables(&params.name)
+            .await
+            .into_iter()
+            .filter(|var| &var.uri == uri)
+            .collect();
+        let Some(definition) = definitions
+            .iter()
+            .find(|var| Some(var.selector.as_str()) == selector)
+            .or_else(|| definitions.first())
+        else {
+            return Ok(params);
+        };
+        let location = Location::new(definition.uri.clone(), definition.range);
+        Ok(WorkspaceSymbol {
+            location: OneOf::Left(location),
+            ..params
+        })
+    }
+
+    async fn diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> tower_lsp::jsonrpc::Result<DocumentDiagnosticReportResult> {
+        let uri = params.text_document.uri;
+        let open_text = self.document_map.read().await.get(&uri).cloned();
+        let report = self
+            .cached_diagnostic_report(
+                &uri,
+                open_text.as_deref(),
+                params.previous_result_id.as_deref(),
+                &mut None,
+            )
+            .await;
+        let report = match report {
+            Some(report) => report,
+            // Unreadable files have no diagnostics.
+            None => {
+                let context = self.diagnostics_context().await;
+                self.document_diagnostic_report(&uri, "", None, &context)
+                    .await
+            }
+        };
+        Ok(DocumentDiagnosticReportResult::Report(report))
+    }
+
+    /// Diagnostics for every open document and workspace file, so problems
+    /// in files that were never opened show up in the client's problems view.
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> tower_lsp::jsonrpc::Result<WorkspaceDiagnosticReportResult> {
+        let previous: HashMap<Url, String> = params
+            .previous_result_ids
+            .into_iter()
+            .map(|previous| (previous.uri, previous.value))
+            .collect();
+        let open: HashMap<Url, String> = self.document_map.read().await.clone();
+        let mut uris: Vec<Url> = open.keys().cloned().collect();
+        for file_uri in self.workspace_file_uris().await {
+            if !open.contains_key(&file_uri) {
+                uris.push(file_uri);
+            }
+        }
+        uris.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+        // Built on the first document that needs validating.
+        let mut context = None;
+        let mut items = Vec::with_capacity(uris.len());
+        for uri in uris {
+            let previous_result_id = previous.get(&uri).map(String::as_str);
+            let open_text = open.get(&uri).map(String::as_str);
+            let Some(report) = self
+                .cached_diagnostic_report(&uri, open_text, previous_result_id, &mut context)
+                .await
+            else {
+                continue;
+            };
+            let item = match report {
+                DocumentDiagnosticReport::Full(report) => {
+                    WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+                        uri,
+                        version: None,
+                        full_document_diagnostic_report: report.full_document_diagnostic_report,
+                    })
+                }
+                DocumentDiagnosticReport::Unchanged(report) => {
+                    WorkspaceDocumentDiagnosticReport::Unchanged(
+                        WorkspaceUnchangedDocumentDiagnosticReport {
+                            uri,
+                            version: None,
+                            unchanged_document_diagnostic_report: report
+                                .unchanged_document_diagnostic_report,
+                        },
+                    )
+                }
+            };
+            items.push(item);
+        }
+
+        Ok(WorkspaceDiagnosticReportResult::Report(
+            WorkspaceDiagnosticReport { items },
+        ))
+    }
 }
 
 impl CssVariableLsp {
@@ -967,7 +2744,7 @@ fn clamp_to_char_boundary(text: &str, mut idx: usize) -> usize {
 }
 
 fn is_word_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+    c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
 }
 
 fn find_context_colon(before_cursor: &str) -> Option<usize> {
@@ -1164,6 +2941,234 @@ fn score_variable_relevance(var_name: &str, property_name: Option<&str>) -> i32
     -1
 }
 
+/// Completion detail for a variable's cascade-sorted definitions: the winning
+/// value followed by any conditional alternatives (e.g. "#fff · dark mode: #111").
+fn format_completion_detail(definitions: &[CssVariable]) -> String {
+    let format_definition = |var: &CssVariable| match format_conditions(&var.conditions) {
+        Some(condition) => format!("{}: {}", condition, var.value),
+        None => var.value.clone(),
+    };
+
+    let mut parts = vec![format_definition(&definitions[0])];
+    for var in &definitions[1..] {
+        if !is_conditional(var) {
+            continue;
+        }
+        let part = format_definition(var);
+        if !parts.contains(&part) {
+            parts.push(part);
+        }
+    }
+    parts.join(" · ")
+}
+
+/// Code action applying a workspace edit.
+fn workspace_edit_action(
+    title: String,
+    kind: CodeActionKind,
+    changes: HashMap<Url, Vec<TextEdit>>,
+    diagnostic: Option<&Diagnostic>,
+) -> CodeAction {
+    CodeAction {
+        title,
+        kind: Some(kind),
+        diagnostics: diagnostic.map(|diagnostic| vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(changes),
+            document_changes: None,
+            change_annotations: None,
+        }),
+        ..Default::default()
+    }
+}
+
+/// Quick fixes replacing a hardcoded literal with each matching variable.
+fn replace_with_variable_actions(uri: &Url, diagnostics: &[Diagnostic]) -> Vec<CodeAction> {
+    let code = NumberOrString::String(HARDCODED_VALUE_CODE.to_string());
+    let mut actions = Vec::new();
+    for diagnostic in diagnostics {
+        if diagnostic.code.as_ref() != Some(&code) {
+            continue;
+        }
+        let candidates: Vec<&str> = diagnostic
+            .data
+            .as_ref()
+            .and_then(|data| data.get("candidates"))
+            .and_then(|candidates| candidates.as_array())
+            .map(|candidates| candidates.iter().filter_map(|c| c.as_str()).collect())
+            .unwrap_or_default();
+        for name in &candidates {
+            let action = workspace_edit_action(
+                format!("Replace with var({})", name),
+                CodeActionKind::QUICKFIX,
+                HashMap::from([(
+                    uri.clone(),
+                    vec![TextEdit {
+                        range: diagnostic.range,
+                        new_text: format!("var({})", name),
+                    }],
+                )]),
+                Some(diagnostic),
+            );
+            actions.push(CodeAction {
+                is_preferred: Some(candidates.len() == 1),
+                ..action
+            });
+        }
+    }
+    actions
+}
+
+/// Result ID of a diagnostic report: a hash of its diagnostics, so a
+/// document whose diagnostics did not change gets the same ID back.
+fn diagnostics_result_id(diagnostics: &[Diagnostic]) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(diagnostics)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// "1 usage", "12 usages"
+fn pluralize(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// Code lens command listing the `filter`ed references of `name` for the
+/// lens at `position`
+fn show_references_command(
+    title: String,
+    name: &str,
+    uri: &Url,
+    position: Position,
+    filter: &str,
+) -> Command {
+    let arguments = ShowReferencesArguments {
+        name: name.to_string(),
+        uri: uri.clone(),
+        position,
+        filter: filter.to_string(),
+    };
+    Command {
+        title,
+        command: SHOW_REFERENCES_COMMAND.to_string(),
+        arguments: serde_json::to_value(arguments)
+            .ok()
+            .map(|argument| vec![argument]),
+    }
+}
+
+/// Whether `def` is the definition whose name starts at `position` in `uri`
+fn is_same_definition(def: &CssVariable, uri: &Url, position: Position) -> bool {
+    def.uri == *uri && def.name_range.unwrap_or(def.range).start == position
+}
+
+/// "[data-theme=dark] · src/theme.css", or just the path for definitions
+/// outside a rule (e.g. inline styles)
+fn symbol_container_name(selector: &str, uri: &Url, options: PathDisplayOptions<'_>) -> String {
+    let path = format_uri_for_display(uri, options);
+    if selector.is_empty() {
+        path
+    } else {
+        format!("{}{}{}", selector, CONTAINER_SEPARATOR, path)
+    }
+}
+
+/// Link to a definition, selecting its name
+fn definition_link(def: &CssVariable, origin_selection_range: Option<Range>) -> LocationLink {
+    LocationLink {
+        origin_selection_range,
+        target_uri: def.uri.clone(),
+        target_range: def.range,
+        target_selection_range: def.name_range.unwrap_or(def.range),
+    }
+}
+
+/// Definition response with links in order, or plain locations for clients
+/// without `linkSupport`.
+fn definition_response(
+    links: Vec<LocationLink>,
+    link_support: bool,
+) -> Option<GotoDefinitionResponse> {
+    if links.is_empty() {
+        return None;
+    }
+    if link_support {
+        return Some(GotoDefinitionResponse::Link(links));
+    }
+    let mut locations: Vec<Location> = links
+        .into_iter()
+        .map(|link| Location::new(link.target_uri, link.target_range))
+        .collect();
+    if locations.len() == 1 {
+        Some(GotoDefinitionResponse::Scalar(locations.remove(0)))
+    } else {
+        Some(GotoDefinitionResponse::Array(locations))
+    }
+}
+
+/// Hover lines with the fully substituted value when it differs from the raw
+/// winning value, e.g. "**Resolved:** `#3b82f6` (via `--brand`)", followed by
+/// the result of evaluating any math functions in it.
+fn format_resolution(resolved: &ResolvedValue, raw_value: &str) -> String {
+    let mut text = String::new();
+    if resolved.value != raw_value {
+        text.push_str(&format!("**Resolved:** `{}`", resolved.value));
+        let via: Vec<String> = resolved
+            .chain
+            .iter()
+            .skip(1)
+            .map(|name| format!("`{}`", name))
+            .collect();
+        if !via.is_empty() {
+            text.push_str(&format!(" (via {})", via.join(" → ")));
+        }
+        text.push('\n');
+    }
+    if let Some(computed) = evaluate_math(&resolved.value) {
+        text.push_str(&format!("**Computed:** `{}`\n", computed));
+    }
+    text
+}
+
+/// Hover line for a `var()` fallback, e.g. "Falls back to `#ccc` when undefined"
+fn format_fallback(fallback: &str) -> String {
+    format!(
+        "**Fallback:** falls back to `{}` when undefined\n",
+        fallback
+    )
+}
+
+/// Inlay hint label for a resolved value (e.g. ": #3b82f6"), truncated to
+/// `max_length` characters of value when non-zero.
+fn format_inlay_hint_label(value: &str, max_length: usize) -> String {
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    if max_length == 0 || value.chars().count() <= max_length {
+        return format!(": {}", value);
+    }
+    let truncated: String = value.chars().take(max_length.saturating_sub(1)).collect();
+    format!(": {}…", truncated.trim_end())
+}
+
+/// Hover section describing an `@property` registration.
+fn format_registration(registration: &RegisteredProperty) -> String {
+    let mut text = String::from("**Registered with `@property`**\n\n");
+    if let Some(syntax) = &registration.syntax {
+        text.push_str(&format!("**Syntax:** `{}`\n", syntax));
+    }
+    if let Some(inherits) = registration.inherits {
+        text.push_str(&format!("**Inherits:** {}\n", inherits));
+    }
+    if let Some(initial_value) = &registration.initial_value {
+        text.push_str(&format!("**Initial value:** `{}`\n", initial_value));
+    }
+    text
+}
+
 fn apply_change_to_text(text: &mut String, change: &TextDocumentContentChangeEvent) {
     if let Some(range) = change.range {
         let start = position_to_offset(text, range.start);
@@ -1202,6 +3207,75 @@ fn find_value_range_in_definition(text: &str, def: &crate::types::CssVariable) -
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runtime_config::build_runtime_config_with_env;
+    use crate::specificity::sort_by_cascade;
+    use tower_lsp::lsp_types::{
+        ClientCapabilities, CodeActionContext, PreviousResultId, TextDocumentIdentifier,
+        TextDocumentItem, TextDocumentPositionParams,
+    };
+    use tower_lsp::LspService;
+
+    /// A server that was never initialized, so its client drops every
+    /// notification and request instead of waiting on a socket.
+    fn test_server(args: &[&str]) -> LspService<CssVariableLsp> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let config = build_runtime_config_with_env(&args, &HashMap::new());
+        LspService::new(|client| CssVariableLsp::new(client, config)).0
+    }
+
+    async fn open_document(server: &CssVariableLsp, uri: &str, text: &str) -> Url {
+        let uri = Url::parse(uri).unwrap();
+        server
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    "css".to_string(),
+                    1,
+                    text.to_string(),
+                ),
+            })
+            .await;
+        uri
+    }
+
+    /// Range of the first occurrence of `needle` in `text`
+    fn range_of(text: &str, needle: &str) -> Range {
+        let start = text.find(needle).unwrap();
+        Range::new(
+            crate::types::offset_to_position(text, start),
+            crate::types::offset_to_position(text, start + needle.len()),
+        )
+    }
+
+    async fn code_actions(
+        server: &CssVariableLsp,
+        uri: &Url,
+        range: Range,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Vec<CodeAction> {
+        let response = server
+            .code_action(CodeActionParams {
+                text_document: TextDocumentIdentifier::new(uri.clone()),
+                range,
+                context: CodeActionContext {
+                    diagnostics,
+                    only: None,
+                    trigger_kind: None,
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap_or_default();
+        response
+            .into_iter()
+            .filter_map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => Some(action),
+                CodeActionOrCommand::Command(_) => None,
+            })
+            .collect()
+    }
 
     fn test_word_extraction(css: &str, cursor_pos: usize) -> Option<String> {
         use tower_lsp::lsp_types::Position;
@@ -1228,6 +3302,705 @@ mod tests {
         }
     }
 
+    #[test]
+    fn completion_detail_lists_conditional_values() {
+        let var = |value: &str, conditions: Vec<crate::types::AtRuleCondition>| CssVariable {
+            name: "--bg".to_string(),
+            value: value.to_string(),
+            uri: Url::parse("file:///test.css").unwrap(),
+            range: Range::default(),
+            name_range: None,
+            value_range: None,
+            selector: ":root".to_string(),
+            conditions,
+            layer: None,
+            important: false,
+            inline: false,
+            deprecated: false,
+            source_position: 0,
+        };
+        let dark = vec![crate::types::AtRuleCondition::new(
+            "media",
+            "(prefers-color-scheme: dark)",
+        )];
+
+        let mut definitions = vec![var("#111", dark.clone()), var("#fff", Vec::new())];
+        sort_by_cascade(&mut definitions);
+        assert_eq!(
+            format_completion_detail(&definitions),
+            "#fff · dark mode: #111"
+        );
+        assert_eq!(
+            format_completion_detail(&[var("#111", dark)]),
+            "dark mode: #111"
+        );
+    }
+
+    #[test]
+    fn resolution_shows_computed_math() {
+        let resolved = ResolvedValue {
+            value: "calc(0.25rem * 4)".to_string(),
+            chain: vec!["--gap".to_string(), "--space".to_string()],
+            complete: true,
+        };
+        assert_eq!(
+            format_resolution(&resolved, "calc(var(--space) * 4)"),
+            "**Resolved:** `calc(0.25rem * 4)` (via `--space`)\n**Computed:** `1rem`\n"
+        );
+
+        let plain = ResolvedValue {
+            value: "1rem".to_string(),
+            chain: vec!["--gap".to_string()],
+            complete: true,
+        };
+        assert_eq!(format_resolution(&plain, "1rem"), "");
+    }
+
+    #[test]
+    fn hover_shows_fallback() {
+        assert_eq!(
+            format_fallback("#ccc"),
+            "**Fallback:** falls back to `#ccc` when undefined\n"
+        );
+    }
+
+    #[test]
+    fn inlay_hint_label_truncates_long_values() {
+        assert_eq!(format_inlay_hint_label("#3b82f6", 30), ": #3b82f6");
+        assert_eq!(
+            format_inlay_hint_label("0 1px  2px\n rgba(0, 0, 0, 0.2)", 12),
+            ": 0 1px 2px r…"
+        );
+        assert_eq!(
+            format_inlay_hint_label("0 1px 2px rgba(0, 0, 0, 0.2)", 0),
+            ": 0 1px 2px rgba(0, 0, 0, 0.2)"
+        );
+    }
+
+    #[test]
+    fn definition_response_falls_back_to_locations() {
+        let link = |path: &str| LocationLink {
+            origin_selection_range: None,
+            target_uri: Url::parse(path).unwrap(),
+            target_range: Range::default(),
+            target_selection_range: Range::default(),
+        };
+        assert!(definition_response(Vec::new(), true).is_none());
+        assert!(matches!(
+            definition_response(vec![link("file:///a.css"), link("file:///b.css")], true),
+            Some(GotoDefinitionResponse::Link(links)) if links.len() == 2
+        ));
+        assert!(matches!(
+            definition_response(vec![link("file:///a.css")], false),
+            Some(GotoDefinitionResponse::Scalar(location)) if location.uri.path() == "/a.css"
+        ));
+        assert!(matches!(
+            definition_response(vec![link("file:///a.css"), link("file:///b.css")], false),
+            Some(GotoDefinitionResponse::Array(locations)) if locations[1].uri.path() == "/b.css"
+        ));
+    }
+
+    #[test]
+    fn code_lens_titles_are_pluralized() {
+        assert_eq!(pluralize(0, "usage"), "0 usages");
+        assert_eq!(pluralize(1, "override"), "1 override");
+        assert_eq!(pluralize(12, "usage"), "12 usages");
+    }
+
+    #[tokio::test]
+    async fn code_lens_commands_return_filtered_locations() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let theme = open_document(
+            server,
+            "file:///theme.css",
+            ":root { --brand: red; }\n.dark { --brand: black; }",
+        )
+        .await;
+        let app = open_document(server, "file:///app.css", ".x { color: var(--brand); }").await;
+
+        let lenses = server
+            .code_lens(CodeLensParams {
+                text_document: TextDocumentIdentifier::new(theme.clone()),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let root_lenses: Vec<&CodeLens> = lenses
+            .iter()
+            .filter(|lens| lens.range.start.line == 0)
+            .collect();
+        assert_eq!(root_lenses.len(), 2);
+
+        let run = |lens: &CodeLens| {
+            let command = lens.command.clone().unwrap();
+            assert_eq!(command.command, SHOW_REFERENCES_COMMAND);
+            let uri = theme.clone();
+            let position = lens.range.start;
+            async move {
+                let result = server
+                    .execute_command(ExecuteCommandParams {
+                        command: command.command,
+                        arguments: command.arguments.unwrap(),
+                        work_done_progress_params: Default::default(),
+                    })
+                    .await
+                    .unwrap()
+                    .unwrap();
+                let result_uri: Url = serde_json::from_value(result["uri"].clone()).unwrap();
+                let result_position: Position =
+                    serde_json::from_value(result["position"].clone()).unwrap();
+                assert_eq!((result_uri, result_position), (uri, position));
+                let locations: Vec<Location> =
+                    serde_json::from_value(result["locations"].clone()).unwrap();
+                (command.title, locations)
+            }
+        };
+
+        let (title, usages) = run(root_lenses[0]).await;
+        assert_eq!(title, "1 usage");
+        assert_eq!(usages.len(), 1);
+        assert_eq!(usages[0].uri, app);
+
+        let (title, overrides) = run(root_lenses[1]).await;
+        assert_eq!(title, "1 override");
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(overrides[0].uri, theme);
+        assert_eq!(overrides[0].range.start.line, 1);
+
+        let unknown = server
+            .execute_command(ExecuteCommandParams {
+                command: "css-variable-lsp.unknown".to_string(),
+                arguments: Vec::new(),
+                work_done_progress_params: Default::default(),
+            })
+            .await;
+        assert!(unknown.is_err());
+    }
+    #[tokio::test]
+    async fn extract_requires_whole_token_selection() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let css = ".btn { border: 1px solid #3b82f6; }";
+        let uri = open_document(server, "file:///app.css", css).await;
+        let is_extract =
+            |action: &CodeAction| action.kind == Some(CodeActionKind::REFACTOR_EXTRACT);
+
+        let partial = code_actions(server, &uri, range_of(css, "82f"), Vec::new()).await;
+        assert!(!partial.iter().any(is_extract));
+
+        let whole = code_actions(server, &uri, range_of(css, "#3b82f6"), Vec::new()).await;
+        let extract = whole.iter().find(|action| is_extract(action)).unwrap();
+        let edits = &extract.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert!(
+            edits
+                .iter()
+                .any(|edit| edit.range == range_of(css, "#3b82f6")
+                    && edit.new_text == "var(--border)")
+        );
+    }
+
+    #[tokio::test]
+    async fn inline_all_rewrites_usages_and_removes_definition() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let css =
+            ":root { --a: red; --b: blue; }\n.x { color: var(--a); }\n.y { color: var(--a); }";
+        let uri = open_document(server, "file:///app.css", css).await;
+        let titled = |actions: &[CodeAction], title: &str| {
+            actions.iter().find(|action| action.title == title).cloned()
+        };
+
+        let actions = code_actions(server, &uri, range_of(css, "--a)"), Vec::new()).await;
+        let inline_all = titled(
+            &actions,
+            "Inline all usages of '--a' and remove its definition",
+        )
+        .unwrap();
+        let mut edits = inline_all.edit.unwrap().changes.unwrap()[&uri].clone();
+        edits.sort_by_key(|edit| edit.range.start);
+        assert_eq!(edits.len(), 3);
+        assert_eq!(edits[0].new_text, "");
+        assert_eq!(edits[1].range.start.line, 1);
+        assert_eq!(edits[2].range.start.line, 2);
+        assert!(edits[1..].iter().all(|edit| edit.new_text == "red"));
+    }
+
+    #[tokio::test]
+    async fn inline_all_is_not_offered_with_nested_fallback_references() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let css = ":root { --a: red; --b: blue; }\n.x { color: var(--a); }\n.y { color: var(--b, var(--a)); }";
+        let uri = open_document(server, "file:///app.css", css).await;
+
+        let actions = code_actions(server, &uri, range_of(css, "--a)"), Vec::new()).await;
+        let titles: Vec<&str> = actions.iter().map(|action| action.title.as_str()).collect();
+        assert!(titles.contains(&"Inline CSS variable '--a'"));
+        assert!(!titles
+            .iter()
+            .any(|title| title.starts_with("Inline all usages")));
+    }
+
+    async fn declaration(server: &CssVariableLsp, uri: &Url, position: Position) -> Vec<Location> {
+        let response = server
+            .goto_declaration(GotoDeclarationParams {
+                text_document_position_params: TextDocumentPositionParams::new(
+                    TextDocumentIdentifier::new(uri.clone()),
+                    position,
+                ),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap();
+        match response {
+            Some(GotoDefinitionResponse::Scalar(location)) => vec![location],
+            Some(GotoDefinitionResponse::Array(locations)) => locations,
+            Some(GotoDefinitionResponse::Link(_)) => panic!("links were not requested"),
+            None => Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn declaration_prefers_property_registration() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let css =
+            "@property --angle { syntax: '<angle>'; inherits: false; initial-value: 0deg; }\n\
+                   :root { --angle: 45deg; }\n.x { rotate: var(--angle); }";
+        let uri = open_document(server, "file:///app.css", css).await;
+
+        let locations = declaration(server, &uri, range_of(css, "--angle)").start).await;
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position::new(0, 0));
+    }
+
+    #[tokio::test]
+    async fn declaration_falls_back_to_cascade_winner_without_root() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let css = ".card { --pad: 4px; }\n.card.wide { --pad: 8px; }\n.x { padding: var(--pad); }";
+        let uri = open_document(server, "file:///app.css", css).await;
+
+        let locations = declaration(server, &uri, range_of(css, "--pad)").start).await;
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start.line, 1);
+    }
+
+    async fn diagnostics(server: &CssVariableLsp, uri: &Url, text: &str) -> Vec<Diagnostic> {
+        let context = server.diagnostics_context().await;
+        server.document_diagnostics(uri, text, &context).await
+    }
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<(String, Option<DiagnosticSeverity>)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| match &diagnostic.code {
+                Some(NumberOrString::String(code)) => (code.clone(), diagnostic.severity),
+                _ => (String::new(), diagnostic.severity),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn undefined_variable_with_fallback_is_informational() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let css = ".x { color: var(--missing, red); }\n.y { color: var(--gone); }";
+        let uri = open_document(server, "file:///app.css", css).await;
+
+        let found = diagnostics(server, &uri, css).await;
+        assert_eq!(
+            codes(&found),
+            vec![
+                (
+                    UNDEFINED_VARIABLE_FALLBACK_CODE.to_string(),
+                    Some(DiagnosticSeverity::INFORMATION)
+                ),
+                (
+                    UNDEFINED_VARIABLE_CODE.to_string(),
+                    Some(DiagnosticSeverity::WARNING)
+                ),
+            ]
+        );
+        assert_eq!(
+            found[0].message,
+            "CSS variable '--missing' is not defined in the workspace; the fallback 'red' is used"
+        );
+    }
+
+    #[tokio::test]
+    async fn fallback_variables_are_checked_only_for_undefined_usages() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let css = ":root { --defined: red; }\n\
+                   .a { color: var(--defined, var(--gone)); }\n\
+                   .b { border: var(--missing, 1px solid var(--gone)); }\n\
+                   .c { color: var(--missing, var(--also-missing, red)); }";
+        let uri = open_document(server, "file:///app.css", css).await;
+
+        let found = diagnostics(server, &uri, css).await;
+        assert_eq!(
+            codes(&found),
+            vec![
+                (
+                    UNDEFINED_VARIABLE_CODE.to_string(),
+                    Some(DiagnosticSeverity::WARNING)
+                ),
+                (
+                    UNDEFINED_FALLBACK_CODE.to_string(),
+                    Some(DiagnosticSeverity::WARNING)
+                ),
+                (
+                    UNDEFINED_VARIABLE_FALLBACK_CODE.to_string(),
+                    Some(DiagnosticSeverity::INFORMATION)
+                ),
+            ]
+        );
+        assert_eq!(found[1].range, range_of(css, "1px solid var(--gone)"));
+        assert_eq!(found[1].data, Some(serde_json::json!({ "name": "--gone" })));
+    }
+
+    #[tokio::test]
+    async fn inline_uses_recorded_fallback_of_undefined_variable() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let css = ".x { margin: var(--missing, calc(1px + 2px)); }";
+        let uri = open_document(server, "file:///app.css", css).await;
+
+        let actions = code_actions(server, &uri, range_of(css, "--missing"), Vec::new()).await;
+        let inline = actions
+            .iter()
+            .find(|action| action.title == "Inline CSS variable '--missing'")
+            .unwrap();
+        let edits = &inline.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert_eq!(edits[0].new_text, "calc(1px + 2px)");
+        assert_eq!(
+            edits[0].range,
+            range_of(css, "var(--missing, calc(1px + 2px))")
+        );
+    }
+
+    #[tokio::test]
+    async fn rename_covers_escaped_spellings() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let css = ":root { --café: brown; }\n.x { color: var(--caf\\e9); }\n\
+                   .y { color: var(--other); }";
+        let uri = open_document(server, "file:///app.css", css).await;
+
+        let edit = server
+            .rename(RenameParams {
+                text_document_position: TextDocumentPositionParams::new(
+                    TextDocumentIdentifier::new(uri.clone()),
+                    range_of(css, "--café").start,
+                ),
+                new_name: "--coffee".to_string(),
+                work_done_progress_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let mut edits = edit.changes.unwrap()[&uri].clone();
+        edits.sort_by_key(|edit| edit.range.start);
+        assert_eq!(
+            edits.iter().map(|edit| edit.range).collect::<Vec<_>>(),
+            vec![range_of(css, "--café"), range_of(css, "--caf\\e9")]
+        );
+        assert!(edits.iter().all(|edit| edit.new_text == "--coffee"));
+    }
+
+    #[tokio::test]
+    async fn diagnostic_severity_overrides_and_off() {
+        let service = test_server(&[
+            "--diagnostic-severity",
+            "undefined-variable=error,undefined-variable-with-fallback=off",
+        ]);
+        let server = service.inner();
+        let css = ".x { color: var(--missing, red); }\n.y { color: var(--gone); }";
+        let uri = open_document(server, "file:///app.css", css).await;
+
+        assert_eq!(
+            codes(&diagnostics(server, &uri, css).await),
+            vec![(
+                UNDEFINED_VARIABLE_CODE.to_string(),
+                Some(DiagnosticSeverity::ERROR)
+            )]
+        );
+    }
+
+    /// Result ID, whether the report is full, and its diagnostic count for
+    /// each document of a workspace pull
+    async fn workspace_pull(
+        server: &CssVariableLsp,
+        previous: &[(Url, String)],
+    ) -> HashMap<Url, (String, bool, usize)> {
+        let params = WorkspaceDiagnosticParams {
+            identifier: None,
+            previous_result_ids: previous
+                .iter()
+                .map(|(uri, value)| PreviousResultId {
+                    uri: uri.clone(),
+                    value: value.clone(),
+                })
+                .collect(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        let WorkspaceDiagnosticReportResult::Report(report) =
+            server.workspace_diagnostic(params).await.unwrap()
+        else {
+            panic!("expected a complete report");
+        };
+        report
+            .items
+            .into_iter()
+            .map(|item| match item {
+                WorkspaceDocumentDiagnosticReport::Full(full) => {
+                    let report = full.full_document_diagnostic_report;
+                    let id = report.result_id.unwrap();
+                    (full.uri, (id, true, report.items.len()))
+                }
+                WorkspaceDocumentDiagnosticReport::Unchanged(unchanged) => {
+                    let id = unchanged.unchanged_document_diagnostic_report.result_id;
+                    (unchanged.uri, (id, false, 0))
+                }
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn workspace_pull_skips_unchanged_documents() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let app = open_document(server, "file:///app.css", ".x { color: var(--x); }").await;
+        let ids = |pull: &HashMap<Url, (String, bool, usize)>| {
+            pull.iter()
+                .map(|(uri, (id, _, _))| (uri.clone(), id.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        let first = workspace_pull(server, &[]).await;
+        assert!(first[&app].1);
+        assert_eq!(first[&app].2, 1);
+
+        // Nothing changed: reported unchanged without validating, so a change
+        // the revision does not track is not picked up.
+        server
+            .manager
+            .add_variable(CssVariable {
+                name: "--x".to_string(),
+                value: "red".to_string(),
+                uri: Url::parse("file:///untracked.css").unwrap(),
+                range: Range::default(),
+                name_range: None,
+                value_range: None,
+                selector: ":root".to_string(),
+                conditions: Vec::new(),
+                layer: None,
+                important: false,
+                inline: false,
+                deprecated: false,
+                source_position: 0,
+            })
+            .await;
+        let second = workspace_pull(server, &ids(&first)).await;
+        assert_eq!(second[&app], (first[&app].0.clone(), false, 0));
+
+        // Opening a document with new definitions revalidates the others.
+        let theme = open_document(server, "file:///theme.css", ":root { --x: blue; }").await;
+        let third = workspace_pull(server, &ids(&second)).await;
+        assert!(third[&app].1);
+        assert_eq!(third[&app].2, 0);
+        assert!(third[&theme].1);
+
+        let fourth = workspace_pull(server, &ids(&third)).await;
+        assert!(fourth.values().all(|(_, full, _)| !full));
+    }
+
+    async fn inlay_hints(server: &CssVariableLsp, uri: &Url, range: Range) -> Vec<InlayHint> {
+        server
+            .inlay_hint(InlayHintParams {
+                text_document: TextDocumentIdentifier::new(uri.clone()),
+                range,
+                work_done_progress_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn inlay_hints_cover_visible_usages_with_markdown_tooltips() {
+        let css = ":root { --gap: 4px; --brand: #3b82f6; }\n\
+                   .a { margin: var(--gap); }\n\
+                   .b { color: var(--brand); }\n\
+                   .c { padding: var(--gap); }";
+        let visible = Range::new(Position::new(1, 0), Position::new(2, 100));
+
+        let service = test_server(&[]);
+        let server = service.inner();
+        let uri = open_document(server, "file:///app.css", css).await;
+        let hints = inlay_hints(server, &uri, visible).await;
+        let labels: Vec<(u32, String)> = hints
+            .iter()
+            .map(|hint| match &hint.label {
+                InlayHintLabel::String(label) => (hint.position.line, label.clone()),
+                InlayHintLabel::LabelParts(_) => panic!("expected a plain label"),
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            vec![(1, ": 4px".to_string()), (2, ": #3b82f6".to_string())]
+        );
+        let Some(InlayHintTooltip::MarkupContent(tooltip)) = &hints[0].tooltip else {
+            panic!("expected a markdown tooltip");
+        };
+        assert_eq!(tooltip.kind, MarkupKind::Markdown);
+        assert!(tooltip.value.starts_with("`--gap` defined in "));
+        assert!(tooltip.value.ends_with(" (`:root`)"));
+
+        let service = test_server(&["--inlay-hints-non-color-only"]);
+        let server = service.inner();
+        let uri = open_document(server, "file:///app.css", css).await;
+        let hints = inlay_hints(server, &uri, visible).await;
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].position.line, 1);
+    }
+
+    #[test]
+    fn diagnostics_result_id_tracks_content() {
+        let diagnostic = |line| Diagnostic {
+            range: Range::new(Position::new(line, 0), Position::new(line, 5)),
+            message: "CSS variable '--x' is not defined in the workspace".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            diagnostics_result_id(&[diagnostic(1)]),
+            diagnostics_result_id(&[diagnostic(1)])
+        );
+        assert_ne!(
+            diagnostics_result_id(&[diagnostic(1)]),
+            diagnostics_result_id(&[diagnostic(2)])
+        );
+        assert_ne!(
+            diagnostics_result_id(&[]),
+            diagnostics_result_id(&[diagnostic(1)])
+        );
+    }
+
+    #[test]
+    fn symbol_container_names_show_selector_and_path() {
+        let roots = vec![PathBuf::from("/app")];
+        let options = || PathDisplayOptions {
+            mode: crate::runtime_config::PathDisplayMode::Relative,
+            abbrev_length: 1,
+            workspace_folder_paths: &roots,
+            root_folder_path: None,
+        };
+        let uri = Url::parse("file:///app/src/theme.css").unwrap();
+        assert_eq!(
+            symbol_container_name("[data-theme=dark]", &uri, options()),
+            "[data-theme=dark] · src/theme.css"
+        );
+        assert_eq!(symbol_container_name("", &uri, options()), "src/theme.css");
+    }
+
+    #[tokio::test]
+    async fn highlights_only_parsed_names() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let css = "/* --caf\\e9 */\n:root { --café: red; }\n.a { color: var(--caf\\e9); content: \"--café\"; }";
+        let uri = open_document(server, "file:///app.css", css).await;
+        let highlight = |position: Position| {
+            let uri = uri.clone();
+            async move {
+                server
+                    .document_highlight(DocumentHighlightParams {
+                        text_document_position_params: TextDocumentPositionParams::new(
+                            TextDocumentIdentifier::new(uri),
+                            position,
+                        ),
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    })
+                    .await
+                    .unwrap()
+            }
+        };
+
+        // The name in front of the character following it
+        let name_range = |needle: &str| {
+            let mut range = range_of(css, needle);
+            range.end.character -= 1;
+            range
+        };
+        let highlights = highlight(range_of(css, "--café:").start).await.unwrap();
+        let highlights: Vec<_> = highlights.iter().map(|h| (h.range, h.kind)).collect();
+        assert_eq!(
+            highlights,
+            vec![
+                (name_range("--café:"), Some(DocumentHighlightKind::WRITE)),
+                (name_range("--caf\\e9)"), Some(DocumentHighlightKind::READ)),
+            ]
+        );
+
+        assert!(highlight(Position::new(0, 5)).await.is_none());
+        assert!(highlight(range_of(css, "--café\"").start).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn workspace_symbols_defer_ranges_to_resolve() {
+        let service = test_server(&[]);
+        let server = service.inner();
+        let css = ":root { --brand: red; }\n.dark { --brand: black; }";
+        let uri = open_document(server, "file:///theme.css", css).await;
+        let params = || WorkspaceSymbolParams {
+            query: "brand".to_string(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let flat = server.workspace_symbols(params()).await.unwrap();
+        assert!(matches!(flat, Some(WorkspaceSymbolResponse::Flat(symbols)) if symbols.len() == 2));
+
+        let capabilities: ClientCapabilities = serde_json::from_value(serde_json::json!({
+            "workspace": { "symbol": { "resolveSupport": { "properties": ["location.range"] } } }
+        }))
+        .unwrap();
+        server
+            .initialize(InitializeParams {
+                capabilities,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let Some(WorkspaceSymbolResponse::Nested(symbols)) =
+            server.workspace_symbols(params()).await.unwrap()
+        else {
+            panic!("expected workspace symbols");
+        };
+        assert_eq!(symbols.len(), 2);
+        assert!(symbols
+            .iter()
+            .all(|symbol| symbol.location == OneOf::Right(WorkspaceLocation { uri: uri.clone() })));
+
+        for symbol in symbols {
+            let selector = symbol.data.as_ref().unwrap()["selector"].clone();
+            let resolved = server.symbol_resolve(symbol).await.unwrap();
+            let OneOf::Left(location) = resolved.location else {
+                panic!("expected a resolved location");
+            };
+            let line = if selector == ":root" { 0 } else { 1 };
+            assert_eq!(
+                (location.uri, location.range.start.line),
+                (uri.clone(), line)
+            );
+        }
+    }
+
     #[test]
     fn test_word_extraction_preserves_fallbacks() {
         // Test extraction of variable name from var() call with fallback
@@ -0,0 +1,93 @@
+//! The `LspService` running `CssVariableLsp`, extended with requests whose
+//! results tower-lsp's `LanguageServer` trait cannot express.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tower_lsp::jsonrpc::{Error, Request, Response};
+use tower_lsp::lsp_types::WorkspaceSymbolParams;
+use tower_lsp::{ClientSocket, ExitedError, LspService};
+use tower_service::Service;
+
+use crate::lsp_server::CssVariableLsp;
+use crate::runtime_config::RuntimeConfig;
+
+/// Answered by `CssVariableLsp::workspace_symbols`, since
+/// `LanguageServer::symbol` cannot return `WorkspaceSymbol`s
+const WORKSPACE_SYMBOL_METHOD: &str = "workspace/symbol";
+
+pub struct CssVariableService {
+    inner: LspService<CssVariableLsp>,
+    server: CssVariableLsp,
+}
+
+impl CssVariableService {
+    pub fn new(runtime_config: RuntimeConfig) -> (Self, ClientSocket) {
+        let mut server = None;
+        let (inner, socket) = LspService::new(|client| {
+            let lsp = CssVariableLsp::new(client, runtime_config);
+            server = Some(lsp.clone());
+            lsp
+        });
+        let server = server.expect("LspService::new builds the server");
+        (Self { inner, server }, socket)
+    }
+}
+
+impl Service<Request> for CssVariableService {
+    type Response = Option<Response>;
+    type Error = ExitedError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        if request.method() != WORKSPACE_SYMBOL_METHOD {
+            return self.inner.call(request);
+        }
+        let (_, id, params) = request.into_parts();
+        let Some(id) = id else {
+            return Box::pin(async { Ok(None) });
+        };
+        let server = self.server.clone();
+        Box::pin(async move {
+            let result = match params.map(serde_json::from_value::<WorkspaceSymbolParams>) {
+                Some(Ok(params)) => server
+                    .workspace_symbols(params)
+                    .await
+                    .map(|symbols| serde_json::to_value(symbols).unwrap_or_default()),
+                _ => Err(Error::invalid_params("Expected workspace symbol params")),
+            };
+            Ok(Some(Response::from_parts(id, result)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime_config::build_runtime_config_with_env;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn answers_workspace_symbol_requests() {
+        let config = build_runtime_config_with_env(&[], &HashMap::new());
+        let (mut service, _socket) = CssVariableService::new(config);
+
+        let request = Request::build(WORKSPACE_SYMBOL_METHOD)
+            .id(1)
+            .params(serde_json::json!({ "query": "brand" }))
+            .finish();
+        let response = service.call(request).await.unwrap().unwrap();
+        let (id, result) = response.into_parts();
+        assert_eq!(id, 1.into());
+        assert_eq!(result.unwrap(), serde_json::json!([]));
+
+        let request = Request::build(WORKSPACE_SYMBOL_METHOD).id(2).finish();
+        let response = service.call(request).await.unwrap().unwrap();
+        assert!(response.is_error());
+    }
+}
@@ -1,5 +1,6 @@
-use css_variable_lsp::{lsp_server, runtime_config};
-use tower_lsp::{LspService, Server};
+use css_variable_lsp::lsp_service::CssVariableService;
+use css_variable_lsp::runtime_config;
+use tower_lsp::Server;
 
 #[tokio::main]
 async fn main() {
@@ -12,8 +13,7 @@ async fn main() {
     let args: Vec<String> = std::env::args().skip(1).collect();
     let runtime_config = runtime_config::build_runtime_config(&args);
 
-    let (service, socket) =
-        LspService::new(|client| lsp_server::CssVariableLsp::new(client, runtime_config.clone()));
+    let (service, socket) = CssVariableService::new(runtime_config);
 
     Server::new(stdin, stdout, socket).serve(service).await;
 }
@@ -1,12 +1,25 @@
-use std::collections::HashMap;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tower_lsp::lsp_types::Url;
 
 use crate::color::parse_color;
+use crate::dependency_graph::{var_calls, var_references, DependencyGraph};
 use crate::dom_tree::DomTree;
-use crate::specificity::sort_by_cascade;
-use crate::types::{Config, CssVariable, CssVariableUsage};
+use crate::layers::{DocumentLayers, LayerOrder};
+use crate::rename::unescape_identifier;
+use crate::specificity::sort_by_cascade_with_layers;
+use crate::types::{Config, CssVariable, CssVariableUsage, RegisteredProperty, ResolvedValue};
+
+/// Deepest chain of nested `var()` references the resolver follows
+const MAX_RESOLVE_DEPTH: usize = 32;
+
+/// Cascade-winning value of every variable, by name
+type CascadeWinners = Arc<HashMap<String, String>>;
 
 /// Manages CSS variables across the workspace
 #[derive(Clone)]
@@ -22,6 +35,18 @@ pub struct CssVariableManager {
 
     /// DOM trees for HTML documents
     dom_trees: Arc<RwLock<HashMap<Url, DomTree>>>,
+
+    /// Cascade layer declarations per document
+    layers: Arc<RwLock<HashMap<Url, DocumentLayers>>>,
+
+    /// Map of property name -> list of `@property` registrations
+    registered_properties: Arc<RwLock<HashMap<String, Vec<RegisteredProperty>>>>,
+
+    /// Bumped whenever definitions or layers change
+    generation: Arc<AtomicU64>,
+
+    /// Cascade winners with the generation they were computed at
+    winners_cache: Arc<RwLock<Option<(u64, CascadeWinners)>>>,
 }
 
 impl CssVariableManager {
@@ -31,6 +56,10 @@ impl CssVariableManager {
             usages: Arc::new(RwLock::new(HashMap::new())),
             config: Arc::new(RwLock::new(config)),
             dom_trees: Arc::new(RwLock::new(HashMap::new())),
+            layers: Arc::new(RwLock::new(HashMap::new())),
+            registered_properties: Arc::new(RwLock::new(HashMap::new())),
+            generation: Arc::new(AtomicU64::new(0)),
+            winners_cache: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -40,6 +69,7 @@ impl CssVariableManager {
         vars.entry(variable.name.clone())
             .or_insert_with(Vec::new)
             .push(variable);
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
     /// Add a variable usage
@@ -51,6 +81,32 @@ impl CssVariableManager {
             .push(usage);
     }
 
+    /// Add an `@property` registration
+    pub async fn add_registered_property(&self, registration: RegisteredProperty) {
+        let mut registrations = self.registered_properties.write().await;
+        registrations
+            .entry(registration.name.clone())
+            .or_insert_with(Vec::new)
+            .push(registration);
+    }
+
+    /// Get all `@property` registrations of a property
+    pub async fn get_registered_properties(&self, name: &str) -> Vec<RegisteredProperty> {
+        let registrations = self.registered_properties.read().await;
+        registrations.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Get all `@property` registrations in a specific document
+    pub async fn get_document_registered_properties(&self, uri: &Url) -> Vec<RegisteredProperty> {
+        let registrations = self.registered_properties.read().await;
+        registrations
+            .values()
+            .flatten()
+            .filter(|r| &r.uri == uri)
+            .cloned()
+            .collect()
+    }
+
     /// Get all definitions of a variable
     pub async fn get_variables(&self, name: &str) -> Vec<CssVariable> {
         let vars = self.variables.read().await;
@@ -63,32 +119,103 @@ impl CssVariableManager {
         usages.get(name).cloned().unwrap_or_default()
     }
 
-    /// Resolve a variable name to a color using cascade ordering and var() chains.
-    pub async fn resolve_variable_color(&self, name: &str) -> Option<tower_lsp::lsp_types::Color> {
-        let mut seen = std::collections::HashSet::new();
-        let mut current = name.to_string();
+    /// Resolve a variable to its cascade-winning value with every nested
+    /// `var()` substituted, or `None` when the variable is not defined.
+    pub async fn resolve_variable(&self, name: &str) -> Option<ResolvedValue> {
+        let winners = self.cascade_winners().await;
+        let value = winners.get(name)?;
+        let mut resolved = ResolvedValue {
+            value: String::new(),
+            chain: vec![name.to_string()],
+            complete: true,
+        };
+        let mut stack = vec![name.to_string()];
+        resolved.value = substitute_vars(value, &winners, &mut stack, &mut resolved);
+        Some(resolved)
+    }
 
-        loop {
-            if seen.contains(&current) {
-                return None;
-            }
-            seen.insert(current.clone());
+    /// Substitute every `var()` in an arbitrary value (e.g. a usage with a
+    /// fallback), using cascade winners and fallbacks.
+    pub async fn resolve_value(&self, value: &str) -> ResolvedValue {
+        let winners = self.cascade_winners().await;
+        let mut resolved = ResolvedValue {
+            value: String::new(),
+            chain: Vec::new(),
+            complete: true,
+        };
+        resolved.value = substitute_vars(value, &winners, &mut Vec::new(), &mut resolved);
+        resolved
+    }
 
-            let mut variables = self.get_variables(&current).await;
-            if variables.is_empty() {
-                return None;
-            }
+    /// Resolve a `var()` usage: the variable's value when it is defined,
+    /// otherwise the usage's fallback, or `None` when it has neither.
+    pub async fn resolve_usage(&self, usage: &CssVariableUsage) -> Option<ResolvedValue> {
+        if let Some(resolved) = self.resolve_variable(&usage.name).await {
+            return Some(resolved);
+        }
+        let fallback = usage.fallback.as_ref()?;
+        let mut resolved = self.resolve_value(&fallback.text).await;
+        resolved.chain.insert(0, usage.name.clone());
+        Some(resolved)
+    }
 
-            sort_by_cascade(&mut variables);
-            let variable = &variables[0];
+    /// Resolve a `var()` usage to a color, through its fallback when the
+    /// variable is undefined.
+    pub async fn resolve_usage_color(
+        &self,
+        usage: &CssVariableUsage,
+    ) -> Option<tower_lsp::lsp_types::Color> {
+        let resolved = self.resolve_usage(usage).await?;
+        if !resolved.complete {
+            return None;
+        }
+        parse_color(&resolved.value)
+    }
 
-            if let Some(next_name) = extract_var_reference(&variable.value) {
-                current = next_name;
-                continue;
-            }
+    /// Resolve a variable name to a color using cascade ordering and var() chains.
+    pub async fn resolve_variable_color(&self, name: &str) -> Option<tower_lsp::lsp_types::Color> {
+        let resolved = self.resolve_variable(name).await?;
+        if !resolved.complete {
+            return None;
+        }
+        parse_color(&resolved.value)
+    }
 
-            return parse_color(&variable.value);
+    /// The definition of a variable that wins the cascade
+    pub async fn get_winning_definition(&self, name: &str) -> Option<CssVariable> {
+        let layer_order = self.get_layer_order().await;
+        let mut definitions = self.get_variables(name).await;
+        sort_by_cascade_with_layers(&mut definitions, &layer_order);
+        definitions.into_iter().next()
+    }
+
+    /// Cascade-winning value of every variable, cached until definitions or
+    /// layers change since hovers, inlay hints and colors resolve per usage.
+    async fn cascade_winners(&self) -> CascadeWinners {
+        // Read before computing: a concurrent change leaves the cache tagged
+        // with a stale generation, so the next call recomputes.
+        let generation = self.generation.load(Ordering::SeqCst);
+        if let Some((cached, winners)) = self.winners_cache.read().await.as_ref() {
+            if *cached == generation {
+                return winners.clone();
+            }
         }
+
+        let layer_order = self.get_layer_order().await;
+        let winners: HashMap<String, String> = {
+            let vars = self.variables.read().await;
+            vars.iter()
+                .filter_map(|(name, definitions)| {
+                    let mut definitions = definitions.clone();
+                    sort_by_cascade_with_layers(&mut definitions, &layer_order);
+                    let winner = definitions.into_iter().next()?;
+                    Some((name.clone(), winner.value))
+                })
+                .collect()
+        };
+        let winners = Arc::new(winners);
+        *self.winners_cache.write().await = Some((generation, winners.clone()));
+        winners
     }
 
     /// Get all variables (for completion)
@@ -97,6 +224,13 @@ impl CssVariableManager {
         vars.values().flatten().cloned().collect()
     }
 
+    /// Graph of `var()` references between all definitions
+    pub async fn get_dependency_graph(&self) -> DependencyGraph {
+        let vars = self.variables.read().await;
+        let all: Vec<CssVariable> = vars.values().flatten().cloned().collect();
+        DependencyGraph::build(&all)
+    }
+
     /// Get all references (definitions + usages) for a variable
     pub async fn get_references(&self, name: &str) -> (Vec<CssVariable>, Vec<CssVariableUsage>) {
         let definitions = self.get_variables(name).await;
@@ -104,11 +238,68 @@ impl CssVariableManager {
         (definitions, usages)
     }
 
+    /// Definitions, usages and `@property` registrations of a variable under
+    /// any spelling of its name, comparing names with CSS escapes decoded so
+    /// `--caf\e9` matches `--café`.
+    pub async fn get_references_unescaped(
+        &self,
+        name: &str,
+    ) -> (
+        Vec<CssVariable>,
+        Vec<CssVariableUsage>,
+        Vec<RegisteredProperty>,
+    ) {
+        let key = unescape_identifier(name);
+        let matches = |candidate: &String| unescape_identifier(candidate) == key;
+        let definitions = {
+            let vars = self.variables.read().await;
+            vars.iter()
+                .filter(|(candidate, _)| matches(candidate))
+                .flat_map(|(_, definitions)| definitions.iter().cloned())
+                .collect()
+        };
+        let usages = {
+            let usages = self.usages.read().await;
+            usages
+                .iter()
+                .filter(|(candidate, _)| matches(candidate))
+                .flat_map(|(_, usages)| usages.iter().cloned())
+                .collect()
+        };
+        let registrations = {
+            let registrations = self.registered_properties.read().await;
+            registrations
+                .iter()
+                .filter(|(candidate, _)| matches(candidate))
+                .flat_map(|(_, registrations)| registrations.iter().cloned())
+                .collect()
+        };
+        (definitions, usages, registrations)
+    }
+
+    /// Usages whose `var()` fallback references `name`, e.g. `var(--a, var(--b))`
+    /// for `--b`; these are not usages of `name` themselves.
+    pub async fn get_fallback_references(&self, name: &str) -> Vec<CssVariableUsage> {
+        let usages = self.usages.read().await;
+        usages
+            .values()
+            .flatten()
+            .filter(|usage| {
+                usage.fallback.as_ref().is_some_and(|fallback| {
+                    var_references(&fallback.text).iter().any(|n| n == name)
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
     /// Remove all data for a document
     pub async fn remove_document(&self, uri: &Url) {
         let mut vars = self.variables.write().await;
         let mut usages = self.usages.write().await;
         let mut dom_trees = self.dom_trees.write().await;
+        let mut layers = self.layers.write().await;
+        let mut registrations = self.registered_properties.write().await;
 
         // Remove variables from this document
         for (_, var_list) in vars.iter_mut() {
@@ -122,7 +313,15 @@ impl CssVariableManager {
         }
         usages.retain(|_, usage_list| !usage_list.is_empty());
 
+        // Remove @property registrations from this document
+        for (_, registration_list) in registrations.iter_mut() {
+            registration_list.retain(|r| &r.uri != uri);
+        }
+        registrations.retain(|_, registration_list| !registration_list.is_empty());
+
         dom_trees.remove(uri);
+        layers.remove(uri);
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
 
     /// Get all variables defined in a specific document
@@ -135,6 +334,112 @@ impl CssVariableManager {
             .collect()
     }
 
+    /// Get all usages in a specific document, in document order
+    pub async fn get_document_usages(&self, uri: &Url) -> Vec<CssVariableUsage> {
+        let usages = self.usages.read().await;
+        let mut document_usages: Vec<CssVariableUsage> = usages
+            .values()
+            .flatten()
+            .filter(|u| &u.uri == uri)
+            .cloned()
+            .collect();
+        document_usages.sort_by_key(|u| u.range.start);
+        document_usages
+    }
+
+    /// Definitions in a document whose name no `var()` in the workspace
+    /// references, excluding names matching the unused allowlist
+    pub async fn get_unused_document_variables(&self, uri: &Url) -> Vec<CssVariable> {
+        let allowlist = {
+            let config = self.config.read().await;
+            let mut builder = GlobSetBuilder::new();
+            for pattern in &config.unused_allowlist {
+                // Patterns may omit the leading dashes ("brand-*").
+                let pattern = if pattern.starts_with("--") {
+                    pattern.clone()
+                } else {
+                    format!("--{}", pattern)
+                };
+                if let Ok(glob) = Glob::new(&pattern) {
+                    builder.add(glob);
+                }
+            }
+            builder.build().unwrap_or_else(|_| GlobSet::empty())
+        };
+
+        // Take the usages lock only after the variables lock is released, so
+        // this never waits on `remove_document` in the opposite order.
+        let definitions = self.get_document_variables(uri).await;
+        let referenced: HashSet<String> = {
+            let usages = self.usages.read().await;
+            let nested = usages
+                .values()
+                .flatten()
+                .filter_map(|usage| usage.fallback.as_ref())
+                .flat_map(|fallback| var_references(&fallback.text));
+            usages.keys().cloned().chain(nested).collect()
+        };
+        definitions
+            .into_iter()
+            .filter(|v| !referenced.contains(&v.name) && !allowlist.is_match(&v.name))
+            .collect()
+    }
+
+    /// Fingerprint of what a document contributes to the rest of the
+    /// workspace: its definitions, `@property` registrations, layers and the
+    /// names it references, ignoring positions. Other documents' diagnostics
+    /// and code lens counts only change when this does.
+    pub async fn document_fingerprint(&self, uri: &Url) -> u64 {
+        let mut definitions: Vec<String> = self
+            .get_document_variables(uri)
+            .await
+            .into_iter()
+            .map(|v| {
+                format!(
+                    "{}\0{}\0{}\0{:?}\0{:?}\0{}\0{}",
+                    v.name, v.value, v.selector, v.conditions, v.layer, v.important, v.inline
+                )
+            })
+            .collect();
+        definitions.sort();
+
+        let mut references: Vec<String> = self
+            .get_document_usages(uri)
+            .await
+            .into_iter()
+            .flat_map(|usage| {
+                let nested = usage
+                    .fallback
+                    .map(|fallback| var_references(&fallback.text))
+                    .unwrap_or_default();
+                std::iter::once(usage.name).chain(nested)
+            })
+            .collect();
+        references.sort();
+
+        let mut registrations: Vec<String> = self
+            .get_document_registered_properties(uri)
+            .await
+            .into_iter()
+            .map(|r| {
+                format!(
+                    "{}\0{:?}\0{:?}\0{:?}",
+                    r.name, r.syntax, r.inherits, r.initial_value
+                )
+            })
+            .collect();
+        registrations.sort();
+
+        let layers = {
+            let layers = self.layers.read().await;
+            layers.get(uri).map(|l| format!("{:?}", l))
+        };
+
+        let mut hasher = DefaultHasher::new();
+        (definitions, references, registrations, layers).hash(&mut hasher);
+        hasher.finish()
+    }
+
     /// Set DOM tree for a document
     pub async fn set_dom_tree(&self, uri: Url, dom_tree: DomTree) {
         let mut dom_trees = self.dom_trees.write().await;
@@ -147,22 +452,68 @@ impl CssVariableManager {
         dom_trees.get(uri).cloned()
     }
 
+    /// Record cascade layers declared by a document
+    pub async fn add_layer_declarations(&self, uri: &Url, declarations: DocumentLayers) {
+        let mut layers = self.layers.write().await;
+        layers.entry(uri.clone()).or_default().extend(declarations);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Workspace-wide cascade layer order
+    pub async fn get_layer_order(&self) -> LayerOrder {
+        let layers = self.layers.read().await;
+        let documents: Vec<(&Url, &DocumentLayers)> = layers.iter().collect();
+        LayerOrder::build(&documents)
+    }
+
     /// Get current configuration
     pub async fn get_config(&self) -> Config {
         self.config.read().await.clone()
     }
 }
 
-fn extract_var_reference(value: &str) -> Option<String> {
-    let trimmed = value.trim();
-    if !trimmed.starts_with("var(") || !trimmed.ends_with(')') {
-        return None;
-    }
-    let inner = trimmed.strip_prefix("var(")?.strip_suffix(')')?.trim();
-    if inner.contains(',') || !inner.starts_with("--") {
-        return None;
+/// Replace every `var()` in `value` with the winning value of the referenced
+/// variable (resolved recursively). Undefined or cyclic references, and those
+/// nested deeper than `MAX_RESOLVE_DEPTH`, use their fallback; without one
+/// they are kept verbatim and mark the result incomplete.
+fn substitute_vars(
+    value: &str,
+    winners: &HashMap<String, String>,
+    stack: &mut Vec<String>,
+    resolved: &mut ResolvedValue,
+) -> String {
+    let mut out = String::new();
+    let mut copied_to = 0;
+    for call in var_calls(value) {
+        out.push_str(&value[copied_to..call.span.start]);
+        let name = call.name;
+        let cyclic = stack.iter().any(|n| n == name);
+        let too_deep = stack.len() >= MAX_RESOLVE_DEPTH;
+        match winners.get(name).filter(|_| !cyclic && !too_deep) {
+            Some(winner) => {
+                if !resolved.chain.iter().any(|n| n == name) {
+                    resolved.chain.push(name.to_string());
+                }
+                stack.push(name.to_string());
+                let substituted = substitute_vars(winner, winners, stack, resolved);
+                stack.pop();
+                out.push_str(&substituted);
+            }
+            None => match call.fallback {
+                Some(fallback) => {
+                    let substituted = substitute_vars(fallback, winners, stack, resolved);
+                    out.push_str(&substituted);
+                }
+                None => {
+                    resolved.complete = false;
+                    out.push_str(&value[call.span.clone()]);
+                }
+            },
+        }
+        copied_to = call.span.end;
     }
-    Some(inner.to_string())
+    out.push_str(&value[copied_to..]);
+    out.trim().to_string()
 }
 
 #[cfg(test)]
@@ -175,12 +526,15 @@ mod tests {
             name: name.to_string(),
             value: value.to_string(),
             selector: selector.to_string(),
+            conditions: Vec::new(),
+            layer: None,
             range: Range::new(Position::new(0, 0), Position::new(0, 10)),
             name_range: None,
             value_range: None,
             uri: Url::parse(uri).unwrap(),
             important: false,
             inline: false,
+            deprecated: false,
             source_position: 0,
         }
     }
@@ -190,8 +544,10 @@ mod tests {
             name: name.to_string(),
             range: Range::new(Position::new(0, 0), Position::new(0, 10)),
             name_range: None,
+            fallback: None,
             uri: Url::parse(uri).unwrap(),
             usage_context: context.to_string(),
+            conditions: Vec::new(),
             dom_node: None,
         }
     }
@@ -371,6 +727,186 @@ mod tests {
         assert_eq!(vars[0].value, "blue");
     }
 
+    #[tokio::test]
+    async fn test_manager_unused_document_variables() {
+        let manager = CssVariableManager::new(Config {
+            unused_allowlist: vec!["brand-*".to_string()],
+            ..Config::default()
+        });
+        let uri = Url::parse("file:///tokens.css").unwrap();
+
+        for name in ["--used", "--unused", "--brand-primary"] {
+            manager
+                .add_variable(create_test_variable(name, "1px", ":root", uri.as_str()))
+                .await;
+        }
+        manager
+            .add_usage(create_test_usage("--used", ".a", "file:///app.css"))
+            .await;
+
+        let unused = manager.get_unused_document_variables(&uri).await;
+        let names: Vec<&str> = unused.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["--unused"]);
+    }
+
+    #[tokio::test]
+    async fn test_manager_unused_counts_references_in_fallbacks() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = Url::parse("file:///app.css").unwrap();
+        let css = ":root { --a: red; --b: blue; --c: green; }\n\
+                   .x { color: var(--a, var(--b)); border: 1px solid var(--c, 1px solid var(--b)); }";
+
+        crate::parsers::parse_css_document(css, &uri, &manager)
+            .await
+            .unwrap();
+
+        assert!(manager.get_unused_document_variables(&uri).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_manager_document_fingerprint_ignores_positions() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = Url::parse("file:///app.css").unwrap();
+        let fingerprint = |css: &'static str| {
+            let manager = manager.clone();
+            let uri = uri.clone();
+            async move {
+                manager.remove_document(&uri).await;
+                crate::parsers::parse_css_document(css, &uri, &manager)
+                    .await
+                    .unwrap();
+                manager.document_fingerprint(&uri).await
+            }
+        };
+
+        let original = fingerprint(":root { --a: red; }\n.x { color: var(--a); }").await;
+        let reformatted =
+            fingerprint("\n\n:root {\n  --a: red;\n}\n\n.x { color: var(--a); }").await;
+        let new_value = fingerprint(":root { --a: blue; }\n.x { color: var(--a); }").await;
+        let new_usage = fingerprint(":root { --a: red; }\n.x { color: var(--a, var(--b)); }").await;
+
+        assert_eq!(original, reformatted);
+        assert_ne!(original, new_value);
+        assert_ne!(original, new_usage);
+    }
+
+    #[tokio::test]
+    async fn test_manager_resolve_nested_values() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = "file:///test.css";
+        for (name, value) in [
+            ("--base", "0.25rem"),
+            ("--space", "calc(var(--base) * 4)"),
+            ("--gap", "var(--missing, var(--space))"),
+            ("--loop-a", "var(--loop-b)"),
+            ("--loop-b", "var(--loop-a, 1px)"),
+        ] {
+            manager
+                .add_variable(create_test_variable(name, value, ":root", uri))
+                .await;
+        }
+
+        let gap = manager.resolve_variable("--gap").await.unwrap();
+        assert_eq!(gap.value, "calc(0.25rem * 4)");
+        assert_eq!(gap.chain, vec!["--gap", "--space", "--base"]);
+        assert!(gap.complete);
+
+        // The cycle falls back to --loop-b's fallback.
+        let looped = manager.resolve_variable("--loop-a").await.unwrap();
+        assert_eq!(looped.value, "1px");
+
+        let usage = manager.resolve_value("var(--undefined)").await;
+        assert!(!usage.complete);
+        assert_eq!(usage.value, "var(--undefined)");
+        assert!(manager.resolve_variable("--undefined").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_manager_resolve_chains_and_fallbacks() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = "file:///test.css";
+        for (name, value) in [
+            ("--blue", "#3b82f6"),
+            ("--primary", "var(--blue)"),
+            ("--button", "var(--primary)"),
+            ("--border", "1px solid var(--button)"),
+            (
+                "--ring",
+                "0 0 0 2px var(--missing, var(--also-missing, red))",
+            ),
+            ("--broken", "var(--missing) 1px"),
+        ] {
+            manager
+                .add_variable(create_test_variable(name, value, ":root", uri))
+                .await;
+        }
+
+        let border = manager.resolve_variable("--border").await.unwrap();
+        assert_eq!(border.value, "1px solid #3b82f6");
+        assert_eq!(
+            border.chain,
+            vec!["--border", "--button", "--primary", "--blue"]
+        );
+        assert!(border.complete);
+
+        let ring = manager.resolve_variable("--ring").await.unwrap();
+        assert_eq!(ring.value, "0 0 0 2px red");
+        assert!(ring.complete);
+
+        let broken = manager.resolve_variable("--broken").await.unwrap();
+        assert_eq!(broken.value, "var(--missing) 1px");
+        assert!(!broken.complete);
+
+        // The cascade winner is used, and the cache follows new definitions.
+        let mut important = create_test_variable("--blue", "navy", ".theme", uri);
+        important.important = true;
+        manager.add_variable(important).await;
+        let border = manager.resolve_variable("--border").await.unwrap();
+        assert_eq!(border.value, "1px solid navy");
+    }
+
+    #[tokio::test]
+    async fn test_manager_resolve_cycles_and_depth_limit() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = "file:///test.css";
+        for (name, value) in [
+            ("--self", "var(--self)"),
+            ("--a", "var(--b)"),
+            ("--b", "var(--c)"),
+            ("--c", "var(--a)"),
+        ] {
+            manager
+                .add_variable(create_test_variable(name, value, ":root", uri))
+                .await;
+        }
+        for depth in 0..=MAX_RESOLVE_DEPTH + 1 {
+            let value = format!("var(--level-{}, deep)", depth + 1);
+            manager
+                .add_variable(create_test_variable(
+                    &format!("--level-{}", depth),
+                    &value,
+                    ":root",
+                    uri,
+                ))
+                .await;
+        }
+
+        let own = manager.resolve_variable("--self").await.unwrap();
+        assert_eq!(own.value, "var(--self)");
+        assert!(!own.complete);
+
+        let cycle = manager.resolve_variable("--a").await.unwrap();
+        assert_eq!(cycle.value, "var(--a)");
+        assert_eq!(cycle.chain, vec!["--a", "--b", "--c"]);
+        assert!(!cycle.complete);
+
+        // The chain is cut at the limit and the fallback there is used.
+        let deep = manager.resolve_variable("--level-0").await.unwrap();
+        assert_eq!(deep.value, "deep");
+        assert_eq!(deep.chain.len(), MAX_RESOLVE_DEPTH);
+        assert!(deep.complete);
+    }
+
     // Note: extract_var_name is not a public function, so we skip testing it directly
 
     #[tokio::test]
@@ -1,7 +1,14 @@
 use tower_lsp::lsp_types::{Range, Url};
 
+use super::stylesheet::{parse_stylesheet, AtRule, Declaration, Node, Stylesheet};
+use super::tokenizer::{Token, TokenKind};
+use crate::layers::{anonymous_layer_name, join_layer_name, DocumentLayers};
 use crate::manager::CssVariableManager;
-use crate::types::{offset_to_position, CssVariable, CssVariableUsage, DOMNodeInfo};
+use crate::specificity::split_selector_list;
+use crate::types::{
+    offset_to_position, AtRuleCondition, CssVariable, CssVariableUsage, DOMNodeInfo,
+    RegisteredProperty, VarFallback,
+};
 
 /// Configuration for parsing CSS snippets
 pub struct CssParseContext<'a> {
@@ -36,487 +43,450 @@ pub async fn parse_css_document(
 
 /// Parse a CSS snippet with a base offset into the full document.
 pub async fn parse_css_snippet(context: CssParseContext<'_>) -> Result<(), String> {
-    extract_definitions(
-        context.css_text,
-        context.full_text,
-        context.uri,
-        context.manager,
-        context.base_offset,
-        context.inline,
-        context.usage_context_override,
-    )
-    .await;
-    extract_usages(
-        context.css_text,
-        context.full_text,
-        context.uri,
-        context.manager,
-        context.base_offset,
-        context.usage_context_override,
-        context.dom_node,
-    )
-    .await;
+    let stylesheet = parse_stylesheet(context.css_text);
+    let mut collector = Collector {
+        context: &context,
+        stylesheet: &stylesheet,
+        variables: Vec::new(),
+        usages: Vec::new(),
+        layers: DocumentLayers::default(),
+        registrations: Vec::new(),
+    };
+    collector.walk(&stylesheet.nodes, &Scope::default());
+
+    let Collector {
+        variables,
+        usages,
+        layers,
+        registrations,
+        ..
+    } = collector;
+    if !layers.names.is_empty() {
+        context
+            .manager
+            .add_layer_declarations(context.uri, layers)
+            .await;
+    }
+    for variable in variables {
+        context.manager.add_variable(variable).await;
+    }
+    for usage in usages {
+        context.manager.add_usage(usage).await;
+    }
+    for registration in registrations {
+        context.manager.add_registered_property(registration).await;
+    }
     Ok(())
 }
 
-async fn extract_definitions(
-    css_text: &str,
-    full_text: &str,
-    uri: &Url,
-    manager: &CssVariableManager,
-    base_offset: usize,
-    inline: bool,
-    selector_override: Option<&str>,
-) {
-    let bytes = css_text.as_bytes();
-    let len = bytes.len();
-    let mut i = 0;
-    let mut in_comment = false;
-    let mut in_string: Option<u8> = None;
-    let mut brace_depth = 0;
-    let mut in_at_rule = false;
-
-    while i < len {
-        if in_comment {
-            if i + 1 < len && bytes[i] == b'*' && bytes[i + 1] == b'/' {
-                in_comment = false;
-                i += 2;
-                continue;
-            }
-            i += 1;
-            continue;
-        }
-
-        if let Some(quote) = in_string {
-            if bytes[i] == b'\\' {
-                i += 2;
-                continue;
-            }
-            if bytes[i] == quote {
-                in_string = None;
-            }
-            i += 1;
-            continue;
-        }
-
-        if i + 1 < len && bytes[i] == b'/' && bytes[i + 1] == b'*' {
-            in_comment = true;
-            i += 2;
-            continue;
-        }
-
-        if bytes[i] == b'"' || bytes[i] == b'\'' {
-            in_string = Some(bytes[i]);
-            i += 1;
-            continue;
-        }
-
-        // Track braces for scope
-        if bytes[i] == b'{' {
-            brace_depth += 1;
-        } else if bytes[i] == b'}' {
-            brace_depth -= 1;
-            if brace_depth < 0 {
-                brace_depth = 0;
-            }
-        }
-
-        // Track @-rules
-        if bytes[i] == b'@' && !in_comment && in_string.is_none() {
-            in_at_rule = true;
-        } else if bytes[i] == b'{' && in_at_rule {
-            in_at_rule = false;
-        }
-
-        if bytes[i] == b'-' && i + 1 < len && bytes[i + 1] == b'-' {
-            let name_start = i;
-            let mut j = i + 2;
-            while j < len && is_ident_char(bytes[j]) {
-                j += 1;
-            }
-            if j == name_start + 2 {
-                i += 2;
-                continue;
-            }
+/// Enclosing rule context for a node in the stylesheet tree
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    selector: Option<String>,
+    conditions: Vec<AtRuleCondition>,
+    layer: Option<String>,
+}
 
-            let name_end = j;
-            let mut k = j;
-            while k < len && bytes[k].is_ascii_whitespace() {
-                k += 1;
-            }
-            if k >= len || bytes[k] != b':' {
-                i = name_end;
-                continue;
-            }
+impl Scope {
+    fn selector(&self) -> String {
+        self.selector.clone().unwrap_or_else(|| ":root".to_string())
+    }
+}
 
-            let mut value_start = k + 1;
-            while value_start < len && bytes[value_start].is_ascii_whitespace() {
-                value_start += 1;
-            }
+/// Walks the stylesheet tree collecting definitions and usages
+struct Collector<'a> {
+    context: &'a CssParseContext<'a>,
+    stylesheet: &'a Stylesheet,
+    variables: Vec<CssVariable>,
+    usages: Vec<CssVariableUsage>,
+    layers: DocumentLayers,
+    registrations: Vec<RegisteredProperty>,
+}
 
-            let mut value_end = value_start;
-            let mut depth = 0i32;
-            let mut val_in_comment = false;
-            let mut val_in_string: Option<u8> = None;
-            while value_end < len {
-                let b = bytes[value_end];
-                if val_in_comment {
-                    if value_end + 1 < len && b == b'*' && bytes[value_end + 1] == b'/' {
-                        val_in_comment = false;
-                        value_end += 2;
-                        continue;
-                    }
-                    value_end += 1;
-                    continue;
-                }
-                if let Some(q) = val_in_string {
-                    if b == b'\\' {
-                        value_end += 2;
-                        continue;
+impl Collector<'_> {
+    fn walk(&mut self, nodes: &[Node], scope: &Scope) {
+        for node in nodes {
+            match node {
+                Node::Declaration(declaration) => {
+                    if declaration.is_custom_property() {
+                        self.add_definition(declaration, scope);
                     }
-                    if b == q {
-                        val_in_string = None;
-                    }
-                    value_end += 1;
-                    continue;
-                }
-                if value_end + 1 < len && b == b'/' && bytes[value_end + 1] == b'*' {
-                    val_in_comment = true;
-                    value_end += 2;
-                    continue;
-                }
-                if b == b'"' || b == b'\'' {
-                    val_in_string = Some(b);
-                    value_end += 1;
-                    continue;
+                    self.collect_usages(declaration.value_tokens.clone(), scope);
                 }
-                if b == b'(' {
-                    depth += 1;
-                    value_end += 1;
-                    continue;
+                Node::StyleRule(rule) => {
+                    let inner = Scope {
+                        selector: Some(resolve_nested_selector(
+                            scope.selector.as_deref(),
+                            &rule.selector,
+                        )),
+                        conditions: scope.conditions.clone(),
+                        layer: scope.layer.clone(),
+                    };
+                    self.walk(&rule.children, &inner);
                 }
-                if b == b')' && depth > 0 {
-                    depth -= 1;
-                    value_end += 1;
-                    continue;
-                }
-                if depth == 0 && (b == b';' || b == b'}') {
-                    break;
+                Node::AtRule(at_rule) => {
+                    self.collect_usages(at_rule.prelude_tokens.clone(), scope);
+                    let block_layer = match at_rule.name.as_str() {
+                        "layer" => self.declare_layers(at_rule, scope),
+                        "import" => {
+                            self.record_import_layer(at_rule, scope);
+                            None
+                        }
+                        "property" => {
+                            self.register_property(at_rule);
+                            None
+                        }
+                        _ => None,
+                    };
+                    if let Some(children) = &at_rule.children {
+                        let mut inner = scope.clone();
+                        if block_layer.is_some() {
+                            inner.layer = block_layer;
+                        }
+                        // Keyframe selectors (`from`, `50%`) are not nested selectors.
+                        if at_rule.name.ends_with("keyframes") {
+                            inner.selector = None;
+                        }
+                        inner
+                            .conditions
+                            .push(AtRuleCondition::new(&at_rule.name, &at_rule.prelude));
+                        self.walk(children, &inner);
+                    }
                 }
-                value_end += 1;
-            }
-
-            let mut value_end_trim = value_end;
-            while value_end_trim > value_start && bytes[value_end_trim - 1].is_ascii_whitespace() {
-                value_end_trim -= 1;
             }
-
-            let name = css_text[name_start..name_end].to_string();
-            let value = css_text[value_start..value_end_trim].trim().to_string();
-            let selector = selector_override
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| find_selector_before(css_text, name_start, in_at_rule));
-
-            let abs_name_start = base_offset + name_start;
-            let abs_name_end = base_offset + name_end;
-            let abs_value_start = base_offset + value_start;
-            let abs_value_end = base_offset + value_end_trim;
-
-            let variable = CssVariable {
-                name,
-                value: value.clone(),
-                uri: uri.clone(),
-                range: Range::new(
-                    offset_to_position(full_text, abs_name_start),
-                    offset_to_position(full_text, abs_value_end),
-                ),
-                name_range: Some(Range::new(
-                    offset_to_position(full_text, abs_name_start),
-                    offset_to_position(full_text, abs_name_end),
-                )),
-                value_range: Some(Range::new(
-                    offset_to_position(full_text, abs_value_start),
-                    offset_to_position(full_text, abs_value_end),
-                )),
-                selector,
-                important: value.to_lowercase().contains("!important"),
-                inline,
-                source_position: abs_name_start,
-            };
-
-            manager.add_variable(variable).await;
-            i = name_end;
-            continue;
         }
-
-        i += 1;
     }
-}
 
-async fn extract_usages(
-    css_text: &str,
-    full_text: &str,
-    uri: &Url,
-    manager: &CssVariableManager,
-    base_offset: usize,
-    usage_context_override: Option<&str>,
-    dom_node: Option<DOMNodeInfo>,
-) {
-    let bytes = css_text.as_bytes();
-    let len = bytes.len();
-    let mut i = 0;
-    let mut in_comment = false;
-    let mut in_string: Option<u8> = None;
-    let mut brace_depth = 0;
-    let mut in_at_rule = false;
-
-    while i < len {
-        if in_comment {
-            if i + 1 < len && bytes[i] == b'*' && bytes[i + 1] == b'/' {
-                in_comment = false;
-                i += 2;
-                continue;
-            }
-            i += 1;
-            continue;
+    /// Record the layers named by `@layer a, b;` or `@layer a { ... }`,
+    /// returning the layer a block belongs to.
+    fn declare_layers(&mut self, at_rule: &AtRule, scope: &Scope) -> Option<String> {
+        let names: Vec<String> = if at_rule.prelude.is_empty() {
+            at_rule.children.as_ref()?;
+            vec![anonymous_layer_name(
+                self.context.base_offset + at_rule.span.start,
+            )]
+        } else {
+            at_rule
+                .prelude
+                .split(',')
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .map(|name| join_layer_name(scope.layer.as_deref(), name))
+                .collect()
+        };
+
+        for name in &names {
+            self.layers.declare(name.clone());
         }
+        at_rule.children.as_ref().and(names.into_iter().next())
+    }
 
-        if let Some(quote) = in_string {
-            if bytes[i] == b'\\' {
-                i += 2;
-                continue;
+    /// Record `@import url(...) layer(name)` so definitions in the imported
+    /// stylesheet are placed in that layer.
+    fn record_import_layer(&mut self, at_rule: &AtRule, scope: &Scope) {
+        let source = self.context.css_text;
+        let tokens: Vec<Token> = self.stylesheet.tokens[at_rule.prelude_tokens.clone()]
+            .iter()
+            .copied()
+            .filter(|t| !t.is_whitespace())
+            .collect();
+
+        let href = match tokens.first() {
+            Some(t) if t.kind == TokenKind::String => unquote(t.text(source)),
+            Some(t) if t.kind == TokenKind::Url => {
+                let text = t.text(source);
+                text[4..text.len() - usize::from(text.ends_with(')'))].trim()
             }
-            if bytes[i] == quote {
-                in_string = None;
+            Some(t) if t.function_name(source) == Some("url") => match tokens.get(1) {
+                Some(arg) if arg.kind == TokenKind::String => unquote(arg.text(source)),
+                _ => return,
+            },
+            _ => return,
+        };
+
+        let mut layer = None;
+        for (idx, token) in tokens.iter().enumerate().skip(1) {
+            if token.kind == TokenKind::Ident && token.text(source).eq_ignore_ascii_case("layer") {
+                layer = Some(anonymous_layer_name(
+                    self.context.base_offset + at_rule.span.start,
+                ));
+                break;
+            }
+            let is_layer_function = token
+                .function_name(source)
+                .is_some_and(|name| name.eq_ignore_ascii_case("layer"));
+            if is_layer_function {
+                let name: String = tokens[idx + 1..]
+                    .iter()
+                    .take_while(|t| t.kind != TokenKind::RightParen)
+                    .map(|t| t.text(source))
+                    .collect();
+                if !name.is_empty() {
+                    layer = Some(name);
+                }
+                break;
             }
-            i += 1;
-            continue;
-        }
-
-        if i + 1 < len && bytes[i] == b'/' && bytes[i + 1] == b'*' {
-            in_comment = true;
-            i += 2;
-            continue;
         }
 
-        if bytes[i] == b'"' || bytes[i] == b'\'' {
-            in_string = Some(bytes[i]);
-            i += 1;
-            continue;
-        }
+        let (layer, url) = match (layer, self.context.uri.join(href)) {
+            (Some(layer), Ok(url)) => (join_layer_name(scope.layer.as_deref(), &layer), url),
+            _ => return,
+        };
+        self.layers.declare(layer.clone());
+        self.layers.imports.push((url, layer));
+    }
 
-        // Track braces for scope
-        if bytes[i] == b'{' {
-            brace_depth += 1;
-        } else if bytes[i] == b'}' {
-            brace_depth -= 1;
-            if brace_depth < 0 {
-                brace_depth = 0;
+    /// Record an `@property --name { ... }` registration and its descriptors.
+    fn register_property(&mut self, at_rule: &AtRule) {
+        let source = self.context.css_text;
+        let mut prelude = self.stylesheet.tokens[at_rule.prelude_tokens.clone()]
+            .iter()
+            .filter(|t| !t.is_whitespace());
+        let name_token = match (prelude.next(), prelude.next()) {
+            (Some(t), None) if t.kind == TokenKind::Ident && t.text(source).starts_with("--") => *t,
+            _ => return,
+        };
+
+        let mut registration = RegisteredProperty {
+            name: name_token.text(source).to_string(),
+            uri: self.context.uri.clone(),
+            range: self.range(at_rule.span.start, at_rule.span.end),
+            name_range: self.range(name_token.start, name_token.end),
+            syntax: None,
+            inherits: None,
+            initial_value: None,
+        };
+        for child in at_rule.children.iter().flatten() {
+            let Node::Declaration(declaration) = child else {
+                continue;
+            };
+            let value = source[declaration.value_span.clone()].trim();
+            match declaration.name.to_ascii_lowercase().as_str() {
+                "syntax" if value.starts_with(['"', '\'']) => {
+                    registration.syntax = Some(unquote(value).to_string())
+                }
+                "inherits" => match value.to_ascii_lowercase().as_str() {
+                    "true" => registration.inherits = Some(true),
+                    "false" => registration.inherits = Some(false),
+                    _ => {}
+                },
+                "initial-value" => registration.initial_value = Some(value.to_string()),
+                _ => {}
             }
         }
+        self.registrations.push(registration);
+    }
 
-        // Track @-rules
-        if bytes[i] == b'@' && !in_comment && in_string.is_none() {
-            in_at_rule = true;
-        } else if bytes[i] == b'{' && in_at_rule {
-            in_at_rule = false;
-        }
+    fn range(&self, start: usize, end: usize) -> Range {
+        let base = self.context.base_offset;
+        Range::new(
+            offset_to_position(self.context.full_text, base + start),
+            offset_to_position(self.context.full_text, base + end),
+        )
+    }
 
-        if is_var_function(bytes, i) {
-            let var_start = i;
-            let mut j = i + 3;
-            while j < len && bytes[j].is_ascii_whitespace() {
-                j += 1;
-            }
-            if j >= len || bytes[j] != b'(' {
+    fn add_definition(&mut self, declaration: &Declaration, scope: &Scope) {
+        let context = self.context;
+        let value = context.css_text[declaration.value_span.clone()].to_string();
+        let selector = context
+            .usage_context_override
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| scope.selector());
+
+        self.variables.push(CssVariable {
+            name: declaration.name.clone(),
+            value,
+            uri: context.uri.clone(),
+            range: self.range(declaration.span.start, declaration.span.end),
+            name_range: Some(self.range(declaration.name_span.start, declaration.name_span.end)),
+            value_range: Some(self.range(declaration.value_span.start, declaration.value_span.end)),
+            selector,
+            conditions: scope.conditions.clone(),
+            layer: scope.layer.clone(),
+            important: declaration.important,
+            inline: context.inline,
+            deprecated: has_deprecated_comment(context.css_text, declaration.name_span.start),
+            source_position: context.base_offset + declaration.name_span.start,
+        });
+    }
+
+    /// Record every top-level `var()` call in a token range. Calls nested in
+    /// another `var()`'s fallback are not reported as separate usages.
+    fn collect_usages(&mut self, range: std::ops::Range<usize>, scope: &Scope) {
+        let tokens: &[Token] = &self.stylesheet.tokens;
+        let source = self.context.css_text;
+        let mut i = range.start;
+        while i < range.end {
+            let token = tokens[i];
+            let is_var = token
+                .function_name(source)
+                .is_some_and(|name| name.eq_ignore_ascii_case("var"));
+            if !is_var {
                 i += 1;
                 continue;
             }
-            let args_start = j + 1;
-            let mut name_start = None;
-            let mut name_end = None;
-            let mut k = args_start;
-            while k < len && bytes[k].is_ascii_whitespace() {
-                k += 1;
-            }
-            if k + 1 < len && bytes[k] == b'-' && bytes[k + 1] == b'-' {
-                name_start = Some(k);
-                k += 2;
-                while k < len && is_ident_char(bytes[k]) {
-                    k += 1;
-                }
-                name_end = Some(k);
-            }
 
-            let mut depth = 1i32;
-            let mut p = args_start;
-            let mut var_in_comment = false;
-            let mut var_in_string: Option<u8> = None;
-            while p < len && depth > 0 {
-                let b = bytes[p];
-                if var_in_comment {
-                    if p + 1 < len && b == b'*' && bytes[p + 1] == b'/' {
-                        var_in_comment = false;
-                        p += 2;
-                        continue;
-                    }
-                    p += 1;
-                    continue;
-                }
-                if let Some(q) = var_in_string {
-                    if b == b'\\' {
-                        p += 2;
-                        continue;
-                    }
-                    if b == q {
-                        var_in_string = None;
-                    }
-                    p += 1;
-                    continue;
-                }
-                if p + 1 < len && b == b'/' && bytes[p + 1] == b'*' {
-                    var_in_comment = true;
-                    p += 2;
-                    continue;
-                }
-                if b == b'"' || b == b'\'' {
-                    var_in_string = Some(b);
-                    p += 1;
-                    continue;
-                }
-                if b == b'(' {
-                    depth += 1;
-                    p += 1;
-                    continue;
-                }
-                if b == b')' {
-                    depth -= 1;
-                    p += 1;
-                    continue;
-                }
-                p += 1;
-            }
-
-            let var_end = p.min(len);
-            if let (Some(ns), Some(ne)) = (name_start, name_end) {
-                let name = css_text[ns..ne].to_string();
-                let usage_context = usage_context_override
+            let close = find_matching_paren(tokens, i, range.end);
+            let call_end = close
+                .map(|idx| tokens[idx].end)
+                .unwrap_or_else(|| tokens[range.end.saturating_sub(1).max(i)].end);
+            let name_token = tokens[i + 1..range.end]
+                .iter()
+                .find(|t| !t.is_whitespace())
+                .filter(|t| t.kind == TokenKind::Ident && t.text(source).starts_with("--"));
+
+            if let Some(name_token) = name_token {
+                let context = self.context;
+                let usage_context = context
+                    .usage_context_override
                     .map(|s| s.to_string())
-                    .unwrap_or_else(|| find_selector_before(css_text, var_start, in_at_rule));
-                let abs_start = base_offset + var_start;
-                let abs_end = base_offset + var_end;
-                let abs_name_start = base_offset + ns;
-                let abs_name_end = base_offset + ne;
-
-                let usage = CssVariableUsage {
-                    name,
-                    uri: uri.clone(),
-                    range: Range::new(
-                        offset_to_position(full_text, abs_start),
-                        offset_to_position(full_text, abs_end),
-                    ),
-                    name_range: Some(Range::new(
-                        offset_to_position(full_text, abs_name_start),
-                        offset_to_position(full_text, abs_name_end),
-                    )),
+                    .unwrap_or_else(|| scope.selector());
+                let args_end = close.unwrap_or(range.end);
+                let content_end = close.map(|idx| tokens[idx].start).unwrap_or(call_end);
+                let fallback = self.fallback(i + 1..args_end, content_end);
+                self.usages.push(CssVariableUsage {
+                    name: name_token.text(source).to_string(),
+                    uri: context.uri.clone(),
+                    range: self.range(token.start, call_end),
+                    name_range: Some(self.range(name_token.start, name_token.end)),
+                    fallback,
                     usage_context,
-                    dom_node: dom_node.clone(),
-                };
-                manager.add_usage(usage).await;
+                    conditions: scope.conditions.clone(),
+                    dom_node: context.dom_node.clone(),
+                });
             }
 
-            i = var_end;
-            continue;
+            i = close.map(|idx| idx + 1).unwrap_or(range.end);
         }
-
-        i += 1;
     }
-}
 
-fn is_var_function(bytes: &[u8], idx: usize) -> bool {
-    if idx + 2 >= bytes.len() {
-        return false;
-    }
-    if !bytes[idx].eq_ignore_ascii_case(&b'v')
-        || !bytes[idx + 1].eq_ignore_ascii_case(&b'a')
-        || !bytes[idx + 2].eq_ignore_ascii_case(&b'r')
-    {
-        return false;
-    }
-    if idx > 0 && is_ident_char(bytes[idx - 1]) {
-        return false;
+    /// Fallback of the `var()` whose arguments are the tokens `args`, ending
+    /// at byte offset `end`: everything after the first top-level comma.
+    fn fallback(&self, args: std::ops::Range<usize>, end: usize) -> Option<VarFallback> {
+        let source = self.context.css_text;
+        let mut depth = 0usize;
+        let comma = self.stylesheet.tokens[args].iter().find(|t| match t.kind {
+            TokenKind::Function | TokenKind::LeftParen => {
+                depth += 1;
+                false
+            }
+            TokenKind::RightParen => {
+                depth = depth.saturating_sub(1);
+                false
+            }
+            TokenKind::Comma => depth == 0,
+            _ => false,
+        })?;
+        let raw = &source[comma.end..end];
+        let text = raw.trim();
+        if text.is_empty() {
+            return None;
+        }
+        let start = comma.end + (raw.len() - raw.trim_start().len());
+        Some(VarFallback {
+            text: text.to_string(),
+            range: self.range(start, start + text.len()),
+        })
     }
-    true
 }
 
-fn is_ident_char(b: u8) -> bool {
-    b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
+/// Resolve a nested rule's selector against its parent per CSS Nesting:
+/// `&` is replaced by the parent selector and selectors without `&` are
+/// treated as descendants of the parent. Parent selector lists are expanded
+/// so the result stays a plain selector list.
+fn resolve_nested_selector(parent: Option<&str>, selector: &str) -> String {
+    let parent = match parent {
+        Some(parent) => parent,
+        None => return selector.to_string(),
+    };
+    let parents = split_selector_list(parent);
+
+    let mut resolved = Vec::new();
+    for nested in split_selector_list(selector) {
+        for parent in &parents {
+            match replace_nesting_selector(nested, parent) {
+                Some(replaced) => resolved.push(replaced),
+                None => resolved.push(format!("{} {}", parent, nested)),
+            }
+        }
+    }
+    resolved.join(", ")
 }
 
-fn find_selector_before(text: &str, offset: usize, in_at_rule: bool) -> String {
-    let before = &text[..offset];
-
-    if in_at_rule {
-        // For variables defined in @-rules, find the @-rule context
-        if let Some(at_pos) = before.rfind('@') {
-            let at_rule_end = before[at_pos..]
-                .find('{')
-                .map(|pos| pos + at_pos)
-                .unwrap_or(before.len());
-            let at_rule = before[at_pos..at_rule_end].trim();
-            return format!("@{}", at_rule);
+/// Replace every `&` outside strings and attribute selectors, or return
+/// `None` if the selector contains no nesting selector.
+fn replace_nesting_selector(selector: &str, parent: &str) -> Option<String> {
+    let mut out = String::with_capacity(selector.len() + parent.len());
+    let mut found = false;
+    let mut quote: Option<char> = None;
+    let mut bracket_depth = 0i32;
+
+    for ch in selector.chars() {
+        if let Some(q) = quote {
+            if ch == q {
+                quote = None;
+            }
+            out.push(ch);
+            continue;
+        }
+        match ch {
+            '"' | '\'' => quote = Some(ch),
+            '[' => bracket_depth += 1,
+            ']' => bracket_depth -= 1,
+            '&' if bracket_depth == 0 => {
+                found = true;
+                out.push_str(parent);
+                continue;
+            }
+            _ => {}
         }
-        return "@unknown".to_string();
+        out.push(ch);
     }
 
-    if let Some(brace_pos) = before.rfind('{') {
-        let start = before[..brace_pos].rfind('}').map(|p| p + 1).unwrap_or(0);
-        let selector_block = before[start..brace_pos].trim();
+    found.then_some(out)
+}
 
-        // Handle complex selectors that might span multiple lines or have nested braces
-        let selector = extract_last_selector(selector_block);
+fn unquote(text: &str) -> &str {
+    let inner = &text[1..];
+    inner
+        .strip_suffix(|c| c == '"' || c == '\'')
+        .unwrap_or(inner)
+}
 
-        if selector.is_empty() {
-            ":root".to_string()
-        } else {
-            selector
-        }
-    } else {
-        ":root".to_string()
-    }
+/// Whether the comment directly before `start` contains `@deprecated`
+/// (e.g. `/* @deprecated use --brand */ --primary: blue;`).
+fn has_deprecated_comment(text: &str, start: usize) -> bool {
+    let Some(body) = text[..start].trim_end().strip_suffix("*/") else {
+        return false;
+    };
+    body.rfind("/*")
+        .is_some_and(|open| body[open + 2..].contains("@deprecated"))
 }
 
-/// Extract the last selector from a selector block, handling complex cases
-fn extract_last_selector(selector_block: &str) -> String {
-    // Split on commas to handle selector lists
-    let selectors: Vec<&str> = selector_block.split(',').map(|s| s.trim()).collect();
-
-    // For each selector, find the last meaningful one
-    for selector in selectors.into_iter().rev() {
-        let cleaned = selector.rsplit('{').next().unwrap_or(selector).trim();
-
-        // Skip empty selectors or CSS at-rules
-        if !cleaned.is_empty() && !cleaned.starts_with('@') {
-            // Clean up multi-line selectors
-            let lines: Vec<&str> = cleaned.lines().collect();
-            for line in lines.into_iter().rev() {
-                let trimmed = line.trim();
-                if !trimmed.is_empty() {
-                    return trimmed.to_string();
+/// Find the index of the `)` closing the function or parenthesis at `open`.
+fn find_matching_paren(tokens: &[Token], open: usize, end: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (idx, token) in tokens.iter().enumerate().take(end).skip(open) {
+        match token.kind {
+            TokenKind::Function | TokenKind::LeftParen => depth += 1,
+            TokenKind::RightParen => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(idx);
                 }
             }
+            _ => {}
         }
     }
-
-    ":root".to_string()
+    None
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::manager::CssVariableManager;
-    use crate::types::Config;
-    use std::collections::HashSet;
+    use crate::types::{format_conditions, AtRuleKind, Config};
+    use std::collections::{HashMap, HashSet};
 
     #[tokio::test]
     async fn parse_css_document_extracts_definitions_and_usages() {
@@ -557,6 +527,239 @@ mod tests {
         let fallback_usages = manager.get_usages("--fallback").await;
         assert_eq!(fallback_usages.len(), 0);
     }
+
+    #[tokio::test]
+    async fn parse_css_document_records_var_fallbacks() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = Url::parse("file:///test.css").unwrap();
+        let text = ".a { color: var(--fg,  #ccc ); }
+                    .b { width: var(--w, calc(100% - (2 * 8px))); }
+                    .c { color: var(--x, var(--y, red)); margin: var(--m); padding: var(--p,); }";
+
+        parse_css_document(text, &uri, &manager).await.unwrap();
+
+        let fallbacks: HashMap<String, Option<VarFallback>> = manager
+            .get_document_usages(&uri)
+            .await
+            .into_iter()
+            .map(|usage| (usage.name, usage.fallback))
+            .collect();
+        let fallback_text = |name: &str| fallbacks[name].as_ref().map(|f| f.text.as_str());
+
+        let fg = fallbacks["--fg"].as_ref().unwrap();
+        assert_eq!(fg.text, "#ccc");
+        let start = crate::types::position_to_offset(text, fg.range.start).unwrap();
+        let end = crate::types::position_to_offset(text, fg.range.end).unwrap();
+        assert_eq!(&text[start..end], "#ccc");
+
+        assert_eq!(fallback_text("--w"), Some("calc(100% - (2 * 8px))"));
+        assert_eq!(fallback_text("--x"), Some("var(--y, red)"));
+        assert_eq!(fallback_text("--m"), None);
+        assert_eq!(fallback_text("--p"), None);
+    }
+
+    #[tokio::test]
+    async fn parse_css_document_records_selector_inside_at_rules() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = Url::parse("file:///test.css").unwrap();
+        let text = "@media (prefers-color-scheme: dark) {\n  @supports (color: red) {\n    \
+                    :root { --bg: #111; color: var(--fg); }\n  }\n}";
+
+        parse_css_document(text, &uri, &manager).await.unwrap();
+
+        let bg = manager.get_variables("--bg").await;
+        assert_eq!(bg.len(), 1);
+        assert_eq!(bg[0].selector, ":root");
+        assert_eq!(
+            bg[0].conditions,
+            vec![
+                AtRuleCondition::new("media", "(prefers-color-scheme: dark)"),
+                AtRuleCondition::new("supports", "(color: red)"),
+            ]
+        );
+        assert_eq!(bg[0].conditions[0].kind, AtRuleKind::Media);
+        assert_eq!(
+            format_conditions(&bg[0].conditions).as_deref(),
+            Some("dark mode and @supports (color: red)")
+        );
+
+        let fg = manager.get_usages("--fg").await;
+        assert_eq!(fg[0].usage_context, ":root");
+        assert_eq!(fg[0].conditions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn parse_css_document_resolves_nested_selectors() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = Url::parse("file:///test.css").unwrap();
+        let text = r#"
+            .card {
+                --card: 1;
+                & .title { --title: 2; color: var(--card); }
+                &:hover { --hover: 3; }
+                .body { --body: 4; }
+                > .icon { --icon: 5; }
+                .theme-dark & { --themed: 6; }
+                @media (min-width: 768px) { --wide: 7; }
+            }
+            .a, .b {
+                & + .c { --list: 8; }
+            }
+        "#;
+
+        parse_css_document(text, &uri, &manager).await.unwrap();
+
+        for (name, expected) in [
+            ("--card", ".card"),
+            ("--title", ".card .title"),
+            ("--hover", ".card:hover"),
+            ("--body", ".card .body"),
+            ("--icon", ".card > .icon"),
+            ("--themed", ".theme-dark .card"),
+            ("--wide", ".card"),
+            ("--list", ".a + .c, .b + .c"),
+        ] {
+            let defs = manager.get_variables(name).await;
+            assert_eq!(defs[0].selector, expected, "selector for {}", name);
+        }
+
+        let usages = manager.get_usages("--card").await;
+        assert_eq!(usages[0].usage_context, ".card .title");
+    }
+
+    #[tokio::test]
+    async fn parse_css_document_keyframes_are_not_nested_selectors() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = Url::parse("file:///test.css").unwrap();
+        let text = ".card { @keyframes pulse { from { --scale: 1; } } }";
+
+        parse_css_document(text, &uri, &manager).await.unwrap();
+
+        let defs = manager.get_variables("--scale").await;
+        assert_eq!(defs[0].selector, "from");
+    }
+
+    #[tokio::test]
+    async fn parse_css_document_records_cascade_layers() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = Url::parse("file:///project/main.css").unwrap();
+        let text = r#"
+            @import url("theme.css") layer(theme);
+            @layer reset, tokens, components;
+            @layer components { :root { --a: 1; } }
+            @layer tokens {
+                :root { --a: 2; }
+                @layer colors { :root { --b: 3; } }
+            }
+            @layer { :root { --c: 4; } }
+            :root { --d: 5; }
+        "#;
+
+        parse_css_document(text, &uri, &manager).await.unwrap();
+
+        let layer_of = |vars: &[CssVariable], value: &str| {
+            vars.iter()
+                .find(|v| v.value == value)
+                .and_then(|v| v.layer.clone())
+        };
+        let a = manager.get_variables("--a").await;
+        assert_eq!(layer_of(&a, "1").as_deref(), Some("components"));
+        assert_eq!(layer_of(&a, "2").as_deref(), Some("tokens"));
+        let b = manager.get_variables("--b").await;
+        assert_eq!(layer_of(&b, "3").as_deref(), Some("tokens.colors"));
+        let c = manager.get_variables("--c").await;
+        assert!(layer_of(&c, "4").unwrap().starts_with("<anonymous"));
+        assert_eq!(manager.get_variables("--d").await[0].layer, None);
+
+        // components is declared after tokens, so it wins for normal declarations.
+        let layers = manager.get_layer_order().await;
+        let mut a = a;
+        crate::specificity::sort_by_cascade_with_layers(&mut a, &layers);
+        assert_eq!(a[0].value, "1");
+
+        let theme_uri = Url::parse("file:///project/theme.css").unwrap();
+        parse_css_document(":root { --e: 6; }", &theme_uri, &manager)
+            .await
+            .unwrap();
+        let e = manager.get_variables("--e").await;
+        let layers = manager.get_layer_order().await;
+        assert_eq!(layers.layer_name(&e[0]).as_deref(), Some("theme"));
+    }
+
+    #[tokio::test]
+    async fn parse_css_document_keeps_full_selector_lists() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = Url::parse("file:///test.css").unwrap();
+        let text = ".a,\n.b > .c { --x: 1px; }";
+
+        parse_css_document(text, &uri, &manager).await.unwrap();
+
+        let defs = manager.get_variables("--x").await;
+        assert_eq!(defs[0].selector, ".a, .b > .c");
+    }
+
+    #[tokio::test]
+    async fn parse_css_document_ignores_comments_and_strings() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = Url::parse("file:///test.css").unwrap();
+        let text = "/* .fake { --commented: 1; } var(--nope) */\n\
+                    .real { content: \"var(--in-string) }\"; --kept: var(--used) !important; }";
+
+        parse_css_document(text, &uri, &manager).await.unwrap();
+
+        assert!(manager.get_variables("--commented").await.is_empty());
+        assert!(manager.get_usages("--nope").await.is_empty());
+        assert!(manager.get_usages("--in-string").await.is_empty());
+
+        let kept = manager.get_variables("--kept").await;
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].selector, ".real");
+        assert_eq!(kept[0].value, "var(--used)");
+        assert!(kept[0].important);
+        assert_eq!(manager.get_usages("--used").await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn parse_css_document_records_property_registrations() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = Url::parse("file:///test.css").unwrap();
+        let text = "@property --angle {\n  syntax: '<angle>';\n  inherits: false;\n  initial-value: 0deg;\n}\n\
+                    .spin { --angle: 45deg; }";
+
+        parse_css_document(text, &uri, &manager).await.unwrap();
+
+        let registrations = manager.get_registered_properties("--angle").await;
+        assert_eq!(registrations.len(), 1);
+        assert_eq!(registrations[0].syntax.as_deref(), Some("<angle>"));
+        assert_eq!(registrations[0].inherits, Some(false));
+        assert_eq!(registrations[0].initial_value.as_deref(), Some("0deg"));
+        assert_eq!(registrations[0].name_range.start.character, 10);
+
+        // Descriptors are not custom properties.
+        let defs = manager.get_variables("--angle").await;
+        assert_eq!(defs.len(), 1);
+        assert_eq!(defs[0].selector, ".spin");
+
+        manager.remove_document(&uri).await;
+        assert!(manager
+            .get_registered_properties("--angle")
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn parse_css_document_marks_deprecated_definitions() {
+        let manager = CssVariableManager::new(Config::default());
+        let uri = Url::parse("file:///test.css").unwrap();
+        let text = ":root {\n  /* @deprecated use --brand */\n  --primary: blue;\n  \
+                    /* brand color */ --brand: navy;\n  --plain: red;\n}";
+
+        parse_css_document(text, &uri, &manager).await.unwrap();
+
+        assert!(manager.get_variables("--primary").await[0].deprecated);
+        assert!(!manager.get_variables("--brand").await[0].deprecated);
+        assert!(!manager.get_variables("--plain").await[0].deprecated);
+    }
 }
 
 #[cfg(test)]
@@ -634,10 +837,13 @@ mod edge_case_tests {
 
         let primary_usages = manager.get_usages("--primary").await;
         assert_eq!(primary_usages.len(), 1);
-        // Fallback values are parsed but not stored in the usage struct
+        let fallback = primary_usages[0].fallback.as_ref().unwrap();
+        assert_eq!(fallback.text, "blue");
 
         let bg_usages = manager.get_usages("--bg").await;
         assert_eq!(bg_usages.len(), 1);
+        let fallback = bg_usages[0].fallback.as_ref().unwrap();
+        assert_eq!(fallback.text, "var(--fallback, #fff)");
     }
 
     #[tokio::test]
@@ -1,5 +1,7 @@
 pub mod css;
 pub mod html;
+pub mod stylesheet;
+pub mod tokenizer;
 
 pub use css::parse_css_document;
 pub use html::parse_html_document;
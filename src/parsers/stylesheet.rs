@@ -0,0 +1,422 @@
+use std::ops::Range;
+
+use super::tokenizer::{tokenize, Token, TokenKind};
+
+/// A stylesheet parsed into a rule/declaration tree.
+///
+/// Nodes refer back into `tokens` by index so callers can walk values and
+/// preludes without re-tokenizing.
+#[derive(Debug, Clone)]
+pub struct Stylesheet {
+    pub tokens: Vec<Token>,
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    StyleRule(StyleRule),
+    AtRule(AtRule),
+    Declaration(Declaration),
+}
+
+/// A qualified rule such as `.card { ... }` (including nested rules)
+#[derive(Debug, Clone)]
+pub struct StyleRule {
+    /// Token indices of the selector prelude (whitespace-trimmed)
+    pub prelude_tokens: Range<usize>,
+    /// Selector text with comments removed and whitespace collapsed
+    pub selector: String,
+    /// Byte span from the start of the prelude to the closing brace
+    pub span: Range<usize>,
+    pub children: Vec<Node>,
+}
+
+/// An at-rule such as `@media (...) { ... }` or `@layer a, b;`
+#[derive(Debug, Clone)]
+pub struct AtRule {
+    /// Lowercased name without the `@`
+    pub name: String,
+    /// Token indices of the prelude (whitespace-trimmed)
+    pub prelude_tokens: Range<usize>,
+    /// Prelude text with comments removed and whitespace collapsed
+    pub prelude: String,
+    /// Byte span from the `@` to the closing brace or semicolon
+    pub span: Range<usize>,
+    /// Block contents, `None` for statement at-rules
+    pub children: Option<Vec<Node>>,
+}
+
+/// A declaration such as `--primary: #fff !important`
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub name: String,
+    pub name_span: Range<usize>,
+    /// Token indices of the value, excluding `!important` and surrounding whitespace
+    pub value_tokens: Range<usize>,
+    pub value_span: Range<usize>,
+    pub important: bool,
+    /// Byte span from the name to the end of the value (excluding the `;`)
+    pub span: Range<usize>,
+}
+
+impl Declaration {
+    pub fn is_custom_property(&self) -> bool {
+        self.name.starts_with("--")
+    }
+}
+
+/// Parse CSS text into a [`Stylesheet`].
+///
+/// The top level accepts declarations as well as rules so the same entry point
+/// serves stylesheets and inline `style` attribute values.
+pub fn parse_stylesheet(source: &str) -> Stylesheet {
+    let tokens = tokenize(source);
+    let nodes = {
+        let mut parser = Parser {
+            source,
+            tokens: &tokens,
+            pos: 0,
+        };
+        parser.parse_block_contents(false)
+    };
+    Stylesheet { tokens, nodes }
+}
+
+/// Serialize tokens back to text, collapsing whitespace runs to single spaces.
+pub fn serialize_tokens(source: &str, tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        if token.is_whitespace() {
+            if !out.is_empty() && !out.ends_with(' ') {
+                out.push(' ');
+            }
+        } else {
+            out.push_str(token.text(source));
+        }
+    }
+    out.trim_end().to_string()
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn kind_at(&self, idx: usize) -> Option<TokenKind> {
+        self.tokens.get(idx).map(|t| t.kind)
+    }
+
+    fn parse_block_contents(&mut self, nested: bool) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        while let Some(kind) = self.kind_at(self.pos) {
+            match kind {
+                TokenKind::Whitespace | TokenKind::Semicolon => self.pos += 1,
+                TokenKind::Cdo | TokenKind::Cdc if !nested => self.pos += 1,
+                TokenKind::RightBrace => {
+                    if nested {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                TokenKind::AtKeyword => nodes.push(Node::AtRule(self.parse_at_rule(nested))),
+                TokenKind::Ident if self.looks_like_declaration() => {
+                    if let Some(declaration) = self.parse_declaration() {
+                        nodes.push(Node::Declaration(declaration));
+                    }
+                }
+                _ => {
+                    if let Some(rule) = self.parse_style_rule() {
+                        nodes.push(Node::StyleRule(rule));
+                    }
+                }
+            }
+        }
+        nodes
+    }
+
+    /// Distinguish `color: red` from a nested rule like `a:hover { ... }`.
+    fn looks_like_declaration(&self) -> bool {
+        let name = self.tokens[self.pos];
+        let mut idx = self.pos + 1;
+        while self.kind_at(idx) == Some(TokenKind::Whitespace) {
+            idx += 1;
+        }
+        if self.kind_at(idx) != Some(TokenKind::Colon) {
+            return false;
+        }
+        if name.text(self.source).starts_with("--") {
+            return true;
+        }
+
+        let mut depth = 0usize;
+        for token in &self.tokens[idx + 1..] {
+            match token.kind {
+                TokenKind::LeftBrace if depth == 0 => return false,
+                TokenKind::Semicolon | TokenKind::RightBrace if depth == 0 => return true,
+                TokenKind::LeftParen
+                | TokenKind::Function
+                | TokenKind::LeftBracket
+                | TokenKind::LeftBrace => depth += 1,
+                TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace => {
+                    depth = depth.saturating_sub(1)
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+
+    /// Advance to the next `;`, `{` or unmatched `}` at nesting depth zero,
+    /// returning its kind without consuming it.
+    fn skip_to_boundary(&mut self, stop_at_brace: bool) -> Option<TokenKind> {
+        let mut depth = 0usize;
+        while let Some(kind) = self.kind_at(self.pos) {
+            match kind {
+                TokenKind::Semicolon if depth == 0 => return Some(kind),
+                TokenKind::LeftBrace if depth == 0 && stop_at_brace => return Some(kind),
+                TokenKind::RightBrace if depth == 0 => return Some(kind),
+                TokenKind::LeftParen
+                | TokenKind::Function
+                | TokenKind::LeftBracket
+                | TokenKind::LeftBrace => depth += 1,
+                TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace => {
+                    depth = depth.saturating_sub(1)
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        None
+    }
+
+    fn trim_whitespace(&self, mut range: Range<usize>) -> Range<usize> {
+        while range.start < range.end && self.tokens[range.start].is_whitespace() {
+            range.start += 1;
+        }
+        while range.end > range.start && self.tokens[range.end - 1].is_whitespace() {
+            range.end -= 1;
+        }
+        range
+    }
+
+    fn byte_span(&self, range: &Range<usize>, fallback: usize) -> Range<usize> {
+        if range.is_empty() {
+            fallback..fallback
+        } else {
+            self.tokens[range.start].start..self.tokens[range.end - 1].end
+        }
+    }
+
+    fn parse_declaration(&mut self) -> Option<Declaration> {
+        let name_token = self.tokens[self.pos];
+        self.pos += 1;
+        while self.kind_at(self.pos) == Some(TokenKind::Whitespace) {
+            self.pos += 1;
+        }
+        if self.kind_at(self.pos) != Some(TokenKind::Colon) {
+            return None;
+        }
+        let colon_end = self.tokens[self.pos].end;
+        self.pos += 1;
+
+        let value_start = self.pos;
+        let terminator = self.skip_to_boundary(false);
+        let full_value = self.trim_whitespace(value_start..self.pos);
+        if terminator == Some(TokenKind::Semicolon) {
+            self.pos += 1;
+        }
+
+        let mut value_tokens = full_value.clone();
+        let mut important = false;
+        if value_tokens.len() >= 2 {
+            let last = self.tokens[value_tokens.end - 1];
+            if last.kind == TokenKind::Ident
+                && last.text(self.source).eq_ignore_ascii_case("important")
+            {
+                let mut bang = value_tokens.end - 2;
+                while bang > value_tokens.start && self.tokens[bang].is_whitespace() {
+                    bang -= 1;
+                }
+                if self.tokens[bang].kind == TokenKind::Delim('!') {
+                    important = true;
+                    value_tokens = self.trim_whitespace(value_tokens.start..bang);
+                }
+            }
+        }
+
+        let value_span = self.byte_span(&value_tokens, colon_end);
+        let end = self.byte_span(&full_value, colon_end).end;
+        Some(Declaration {
+            name: name_token.text(self.source).to_string(),
+            name_span: name_token.start..name_token.end,
+            value_tokens,
+            value_span,
+            important,
+            span: name_token.start..end,
+        })
+    }
+
+    fn parse_at_rule(&mut self, nested: bool) -> AtRule {
+        let at_token = self.tokens[self.pos];
+        let name = at_token
+            .at_keyword_name(self.source)
+            .unwrap_or_default()
+            .to_lowercase();
+        self.pos += 1;
+
+        let prelude_start = self.pos;
+        let boundary = self.skip_to_boundary(true);
+        let prelude_tokens = self.trim_whitespace(prelude_start..self.pos);
+        let prelude = serialize_tokens(self.source, &self.tokens[prelude_tokens.clone()]);
+
+        let mut children = None;
+        match boundary {
+            Some(TokenKind::Semicolon) => self.pos += 1,
+            Some(TokenKind::LeftBrace) => {
+                self.pos += 1;
+                children = Some(self.parse_block_contents(true));
+                self.pos += usize::from(self.kind_at(self.pos) == Some(TokenKind::RightBrace));
+            }
+            Some(TokenKind::RightBrace) if !nested => self.pos += 1,
+            _ => {}
+        }
+
+        let end = self.tokens[..self.pos]
+            .last()
+            .map(|t| t.end)
+            .unwrap_or(at_token.end);
+        AtRule {
+            name,
+            prelude_tokens,
+            prelude,
+            span: at_token.start..end.max(at_token.end),
+            children,
+        }
+    }
+
+    fn parse_style_rule(&mut self) -> Option<StyleRule> {
+        let prelude_start = self.pos;
+        match self.skip_to_boundary(true) {
+            Some(TokenKind::LeftBrace) => {}
+            Some(TokenKind::Semicolon) => {
+                // Stray tokens without a block; drop them like the spec does.
+                self.pos += 1;
+                return None;
+            }
+            _ => return None,
+        }
+
+        let prelude_tokens = self.trim_whitespace(prelude_start..self.pos);
+        let selector = serialize_tokens(self.source, &self.tokens[prelude_tokens.clone()]);
+        let start = self.tokens[prelude_start].start;
+        self.pos += 1;
+        let children = self.parse_block_contents(true);
+        let mut end = self.tokens[self.pos - 1].end;
+        if self.kind_at(self.pos) == Some(TokenKind::RightBrace) {
+            end = self.tokens[self.pos].end;
+            self.pos += 1;
+        }
+
+        Some(StyleRule {
+            prelude_tokens,
+            selector,
+            span: start..end,
+            children,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declarations(nodes: &[Node]) -> Vec<&Declaration> {
+        nodes
+            .iter()
+            .filter_map(|n| match n {
+                Node::Declaration(d) => Some(d),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_rules_and_declarations() {
+        let css = ":root { --a: 1px; color: red !important }";
+        let sheet = parse_stylesheet(css);
+        assert_eq!(sheet.nodes.len(), 1);
+        let Node::StyleRule(rule) = &sheet.nodes[0] else {
+            panic!("expected style rule");
+        };
+        assert_eq!(rule.selector, ":root");
+        let decls = declarations(&rule.children);
+        assert_eq!(decls.len(), 2);
+        assert_eq!(decls[0].name, "--a");
+        assert_eq!(&css[decls[0].value_span.clone()], "1px");
+        assert!(decls[1].important);
+        assert_eq!(&css[decls[1].value_span.clone()], "red");
+    }
+
+    #[test]
+    fn distinguishes_nested_rules_from_declarations() {
+        let css = ".card { color: red; a:hover { --x: 1 } & .title { --y: 2 } }";
+        let sheet = parse_stylesheet(css);
+        let Node::StyleRule(rule) = &sheet.nodes[0] else {
+            panic!("expected style rule");
+        };
+        let nested: Vec<&str> = rule
+            .children
+            .iter()
+            .filter_map(|n| match n {
+                Node::StyleRule(r) => Some(r.selector.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(nested, vec!["a:hover", "& .title"]);
+        assert_eq!(declarations(&rule.children).len(), 1);
+    }
+
+    #[test]
+    fn parses_at_rules_with_and_without_blocks() {
+        let css = "@layer reset, tokens;\n@media (min-width:  768px) { .a { --b: 1 } }";
+        let sheet = parse_stylesheet(css);
+        let at_rules: Vec<&AtRule> = sheet
+            .nodes
+            .iter()
+            .filter_map(|n| match n {
+                Node::AtRule(r) => Some(r),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(at_rules.len(), 2);
+        assert_eq!(at_rules[0].name, "layer");
+        assert_eq!(at_rules[0].prelude, "reset, tokens");
+        assert!(at_rules[0].children.is_none());
+        assert_eq!(at_rules[1].name, "media");
+        assert_eq!(at_rules[1].prelude, "(min-width: 768px)");
+        assert_eq!(at_rules[1].children.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn custom_property_values_may_contain_braces() {
+        let css = ":root { --obj: { a: b }; --next: 1 }";
+        let sheet = parse_stylesheet(css);
+        let Node::StyleRule(rule) = &sheet.nodes[0] else {
+            panic!("expected style rule");
+        };
+        let decls = declarations(&rule.children);
+        assert_eq!(decls.len(), 2);
+        assert_eq!(&css[decls[0].value_span.clone()], "{ a: b }");
+    }
+
+    #[test]
+    fn recovers_from_unclosed_blocks() {
+        let sheet = parse_stylesheet(":root { --valid: blue;");
+        let Node::StyleRule(rule) = &sheet.nodes[0] else {
+            panic!("expected style rule");
+        };
+        assert_eq!(declarations(&rule.children).len(), 1);
+    }
+}
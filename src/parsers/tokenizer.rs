@@ -0,0 +1,521 @@
+/// Kinds of tokens produced by the CSS tokenizer (CSS Syntax Module Level 3, §4).
+///
+/// Comments are consumed by the tokenizer and never emitted, matching the spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Ident,
+    /// A function name including the opening parenthesis (e.g. `var(`)
+    Function,
+    /// An at-keyword including the `@` (e.g. `@media`)
+    AtKeyword,
+    Hash,
+    String,
+    BadString,
+    Url,
+    BadUrl,
+    Delim(char),
+    Number,
+    Percentage,
+    Dimension,
+    Whitespace,
+    Cdo,
+    Cdc,
+    Colon,
+    Semicolon,
+    Comma,
+    LeftBracket,
+    RightBracket,
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+}
+
+/// A token with its byte span in the tokenized text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Token {
+    /// Raw source text covered by this token
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+
+    /// Function name without the trailing `(`, if this is a function token
+    pub fn function_name<'a>(&self, source: &'a str) -> Option<&'a str> {
+        if self.kind != TokenKind::Function {
+            return None;
+        }
+        let text = self.text(source);
+        Some(&text[..text.len() - 1])
+    }
+
+    /// At-rule name without the leading `@`, if this is an at-keyword token
+    pub fn at_keyword_name<'a>(&self, source: &'a str) -> Option<&'a str> {
+        if self.kind != TokenKind::AtKeyword {
+            return None;
+        }
+        Some(&self.text(source)[1..])
+    }
+
+    pub fn is_whitespace(&self) -> bool {
+        self.kind == TokenKind::Whitespace
+    }
+}
+
+/// Tokenize CSS text into a flat list of tokens.
+pub fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokenizer = Tokenizer {
+        input,
+        bytes: input.as_bytes(),
+        pos: 0,
+    };
+    let mut tokens = Vec::new();
+    while let Some(token) = tokenizer.next_token() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+struct Tokenizer<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Tokenizer<'_> {
+    fn peek(&self, n: usize) -> Option<u8> {
+        self.bytes.get(self.pos + n).copied()
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        self.skip_comments();
+        let start = self.pos;
+        let b = self.peek(0)?;
+
+        let kind = match b {
+            b if is_whitespace(b) => {
+                while self.peek(0).is_some_and(is_whitespace) {
+                    self.pos += 1;
+                }
+                TokenKind::Whitespace
+            }
+            b'"' | b'\'' => self.consume_string(b),
+            b'#' => {
+                if self.peek(1).is_some_and(is_ident_char) || self.valid_escape_at(1) {
+                    self.pos += 1;
+                    self.consume_ident_sequence();
+                    TokenKind::Hash
+                } else {
+                    self.consume_delim()
+                }
+            }
+            b'(' => self.single(TokenKind::LeftParen),
+            b')' => self.single(TokenKind::RightParen),
+            b'[' => self.single(TokenKind::LeftBracket),
+            b']' => self.single(TokenKind::RightBracket),
+            b'{' => self.single(TokenKind::LeftBrace),
+            b'}' => self.single(TokenKind::RightBrace),
+            b',' => self.single(TokenKind::Comma),
+            b':' => self.single(TokenKind::Colon),
+            b';' => self.single(TokenKind::Semicolon),
+            b'+' | b'.' => {
+                if self.starts_number() {
+                    self.consume_numeric()
+                } else {
+                    self.consume_delim()
+                }
+            }
+            b'-' => {
+                if self.starts_number() {
+                    self.consume_numeric()
+                } else if self.peek(1) == Some(b'-') && self.peek(2) == Some(b'>') {
+                    self.pos += 3;
+                    TokenKind::Cdc
+                } else if self.starts_ident_at(0) {
+                    self.consume_ident_like()
+                } else {
+                    self.consume_delim()
+                }
+            }
+            b'<' => {
+                if self.bytes[self.pos..].starts_with(b"<!--") {
+                    self.pos += 4;
+                    TokenKind::Cdo
+                } else {
+                    self.consume_delim()
+                }
+            }
+            b'@' => {
+                if self.starts_ident_at(1) {
+                    self.pos += 1;
+                    self.consume_ident_sequence();
+                    TokenKind::AtKeyword
+                } else {
+                    self.consume_delim()
+                }
+            }
+            b'\\' => {
+                if self.valid_escape_at(0) {
+                    self.consume_ident_like()
+                } else {
+                    self.consume_delim()
+                }
+            }
+            b if b.is_ascii_digit() => self.consume_numeric(),
+            b if is_ident_start(b) => self.consume_ident_like(),
+            _ => self.consume_delim(),
+        };
+
+        Some(Token {
+            kind,
+            start,
+            end: self.pos,
+        })
+    }
+
+    fn single(&mut self, kind: TokenKind) -> TokenKind {
+        self.pos += 1;
+        kind
+    }
+
+    fn consume_delim(&mut self) -> TokenKind {
+        let ch = self.input[self.pos..].chars().next().unwrap_or('\u{FFFD}');
+        self.pos += ch.len_utf8();
+        TokenKind::Delim(ch)
+    }
+
+    fn skip_comments(&mut self) {
+        while self.peek(0) == Some(b'/') && self.peek(1) == Some(b'*') {
+            match self.input[self.pos + 2..].find("*/") {
+                Some(end) => self.pos += 2 + end + 2,
+                None => self.pos = self.bytes.len(),
+            }
+        }
+    }
+
+    fn valid_escape_at(&self, n: usize) -> bool {
+        self.peek(n) == Some(b'\\') && self.peek(n + 1).is_some_and(|b| b != b'\n')
+    }
+
+    fn starts_ident_at(&self, n: usize) -> bool {
+        match self.peek(n) {
+            Some(b'-') => {
+                self.peek(n + 1)
+                    .is_some_and(|b| is_ident_start(b) || b == b'-')
+                    || self.valid_escape_at(n + 1)
+            }
+            Some(b'\\') => self.valid_escape_at(n),
+            Some(b) => is_ident_start(b),
+            None => false,
+        }
+    }
+
+    fn starts_number(&self) -> bool {
+        let digit_at = |n: usize| self.peek(n).is_some_and(|b| b.is_ascii_digit());
+        match self.peek(0) {
+            Some(b'+') | Some(b'-') => digit_at(1) || (self.peek(1) == Some(b'.') && digit_at(2)),
+            Some(b'.') => digit_at(1),
+            Some(b) => b.is_ascii_digit(),
+            None => false,
+        }
+    }
+
+    fn consume_escape(&mut self) {
+        // Assumes the backslash has already been consumed.
+        if self.peek(0).is_some_and(|b| b.is_ascii_hexdigit()) {
+            let mut count = 0;
+            while count < 6 && self.peek(0).is_some_and(|b| b.is_ascii_hexdigit()) {
+                self.pos += 1;
+                count += 1;
+            }
+            if self.peek(0).is_some_and(is_whitespace) {
+                self.pos += 1;
+            }
+        } else if let Some(ch) = self.input[self.pos..].chars().next() {
+            self.pos += ch.len_utf8();
+        }
+    }
+
+    fn consume_ident_sequence(&mut self) {
+        loop {
+            match self.peek(0) {
+                Some(b) if is_ident_char(b) => self.pos += 1,
+                Some(b'\\') if self.valid_escape_at(0) => {
+                    self.pos += 1;
+                    self.consume_escape();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn consume_ident_like(&mut self) -> TokenKind {
+        let start = self.pos;
+        self.consume_ident_sequence();
+        if self.peek(0) != Some(b'(') {
+            return TokenKind::Ident;
+        }
+
+        let is_url = self.input[start..self.pos].eq_ignore_ascii_case("url");
+        self.pos += 1;
+        if !is_url {
+            return TokenKind::Function;
+        }
+
+        let mut lookahead = self.pos;
+        while self
+            .bytes
+            .get(lookahead)
+            .copied()
+            .is_some_and(is_whitespace)
+        {
+            lookahead += 1;
+        }
+        match self.bytes.get(lookahead) {
+            Some(b'"') | Some(b'\'') => TokenKind::Function,
+            _ => {
+                self.pos = lookahead;
+                self.consume_url()
+            }
+        }
+    }
+
+    fn consume_url(&mut self) -> TokenKind {
+        loop {
+            match self.peek(0) {
+                None => return TokenKind::Url,
+                Some(b')') => {
+                    self.pos += 1;
+                    return TokenKind::Url;
+                }
+                Some(b) if is_whitespace(b) => {
+                    while self.peek(0).is_some_and(is_whitespace) {
+                        self.pos += 1;
+                    }
+                    match self.peek(0) {
+                        None => return TokenKind::Url,
+                        Some(b')') => {
+                            self.pos += 1;
+                            return TokenKind::Url;
+                        }
+                        _ => {
+                            self.consume_bad_url_remnants();
+                            return TokenKind::BadUrl;
+                        }
+                    }
+                }
+                Some(b'"') | Some(b'\'') | Some(b'(') => {
+                    self.consume_bad_url_remnants();
+                    return TokenKind::BadUrl;
+                }
+                Some(b'\\') => {
+                    if self.valid_escape_at(0) {
+                        self.pos += 1;
+                        self.consume_escape();
+                    } else {
+                        self.consume_bad_url_remnants();
+                        return TokenKind::BadUrl;
+                    }
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    fn consume_bad_url_remnants(&mut self) {
+        loop {
+            match self.peek(0) {
+                None => return,
+                Some(b')') => {
+                    self.pos += 1;
+                    return;
+                }
+                Some(b'\\') if self.valid_escape_at(0) => {
+                    self.pos += 1;
+                    self.consume_escape();
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    fn consume_string(&mut self, quote: u8) -> TokenKind {
+        self.pos += 1;
+        loop {
+            match self.peek(0) {
+                None => return TokenKind::String,
+                Some(b) if b == quote => {
+                    self.pos += 1;
+                    return TokenKind::String;
+                }
+                Some(b'\n') | Some(b'\r') | Some(0x0c) => return TokenKind::BadString,
+                Some(b'\\') => {
+                    self.pos += 1;
+                    match self.peek(0) {
+                        None => {}
+                        Some(b'\n') => self.pos += 1,
+                        Some(_) => self.consume_escape(),
+                    }
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    fn consume_numeric(&mut self) -> TokenKind {
+        if matches!(self.peek(0), Some(b'+') | Some(b'-')) {
+            self.pos += 1;
+        }
+        while self.peek(0).is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if self.peek(0) == Some(b'.') && self.peek(1).is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+            while self.peek(0).is_some_and(|b| b.is_ascii_digit()) {
+                self.pos += 1;
+            }
+        }
+        if matches!(self.peek(0), Some(b'e') | Some(b'E')) {
+            let sign = usize::from(matches!(self.peek(1), Some(b'+') | Some(b'-')));
+            if self.peek(1 + sign).is_some_and(|b| b.is_ascii_digit()) {
+                self.pos += 1 + sign;
+                while self.peek(0).is_some_and(|b| b.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+            }
+        }
+
+        if self.starts_ident_at(0) {
+            self.consume_ident_sequence();
+            TokenKind::Dimension
+        } else if self.peek(0) == Some(b'%') {
+            self.pos += 1;
+            TokenKind::Percentage
+        } else {
+            TokenKind::Number
+        }
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r' | 0x0c)
+}
+
+fn is_ident_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_' || b >= 0x80
+}
+
+fn is_ident_char(b: u8) -> bool {
+    is_ident_start(b) || b.is_ascii_digit() || b == b'-'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        tokenize(input)
+            .into_iter()
+            .filter(|t| !t.is_whitespace())
+            .map(|t| t.kind)
+            .collect()
+    }
+
+    #[test]
+    fn tokenizes_custom_property_declaration() {
+        let input = "--primary-color: var(--base, #fff);";
+        let tokens = tokenize(input);
+        assert_eq!(tokens[0].kind, TokenKind::Ident);
+        assert_eq!(tokens[0].text(input), "--primary-color");
+        assert_eq!(
+            kinds(input),
+            vec![
+                TokenKind::Ident,
+                TokenKind::Colon,
+                TokenKind::Function,
+                TokenKind::Ident,
+                TokenKind::Comma,
+                TokenKind::Hash,
+                TokenKind::RightParen,
+                TokenKind::Semicolon,
+            ]
+        );
+        let function = tokens
+            .iter()
+            .find(|t| t.kind == TokenKind::Function)
+            .unwrap();
+        assert_eq!(function.function_name(input), Some("var"));
+    }
+
+    #[test]
+    fn skips_comments_and_keeps_strings_opaque() {
+        let input = "/* var(--a) */ content: \"var(--b) }\";";
+        assert_eq!(
+            kinds(input),
+            vec![
+                TokenKind::Ident,
+                TokenKind::Colon,
+                TokenKind::String,
+                TokenKind::Semicolon,
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenizes_numbers_and_dimensions() {
+        assert_eq!(
+            kinds("1.5rem 50% -2 .5e2px"),
+            vec![
+                TokenKind::Dimension,
+                TokenKind::Percentage,
+                TokenKind::Number,
+                TokenKind::Dimension,
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenizes_urls_and_at_keywords() {
+        let input = "@import url(foo.css) layer(base);";
+        let tokens = tokenize(input);
+        assert_eq!(tokens[0].at_keyword_name(input), Some("import"));
+        assert_eq!(
+            kinds(input),
+            vec![
+                TokenKind::AtKeyword,
+                TokenKind::Url,
+                TokenKind::Function,
+                TokenKind::Ident,
+                TokenKind::RightParen,
+                TokenKind::Semicolon,
+            ]
+        );
+        assert_eq!(kinds("url( \"a b\" )")[0], TokenKind::Function);
+        assert_eq!(kinds("url(a b)"), vec![TokenKind::BadUrl]);
+    }
+
+    #[test]
+    fn unterminated_string_becomes_bad_string_at_newline() {
+        assert_eq!(
+            kinds("'abc\n--x"),
+            vec![TokenKind::BadString, TokenKind::Ident]
+        );
+    }
+
+    #[test]
+    fn handles_escapes_and_non_ascii_identifiers() {
+        let input = "--café \\31 0px";
+        let tokens: Vec<_> = tokenize(input)
+            .into_iter()
+            .filter(|t| !t.is_whitespace())
+            .collect();
+        assert_eq!(tokens[0].text(input), "--café");
+        assert_eq!(tokens[1].kind, TokenKind::Ident);
+        assert_eq!(tokens[1].text(input), "\\31 0px");
+    }
+}
@@ -0,0 +1,400 @@
+use crate::color::parse_color;
+use crate::parsers::tokenizer::{tokenize, Token, TokenKind};
+
+/// A parsed `@property` `syntax` descriptor (CSS Properties and Values API, §5)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertySyntax {
+    /// `"*"`: any value is accepted
+    Universal,
+    /// One or more `|`-separated alternatives
+    Alternatives(Vec<SyntaxComponent>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxComponent {
+    pub kind: ComponentKind,
+    pub multiplier: Option<Multiplier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComponentKind {
+    /// A data type name without brackets (e.g., "length")
+    DataType(String),
+    /// A literal keyword (e.g., "auto")
+    Keyword(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplier {
+    /// `+`: space-separated list
+    Space,
+    /// `#`: comma-separated list
+    Comma,
+}
+
+const CSS_WIDE_KEYWORDS: [&str; 5] = ["initial", "inherit", "unset", "revert", "revert-layer"];
+
+const MATH_FUNCTIONS: [&str; 9] = [
+    "calc", "min", "max", "clamp", "round", "mod", "rem", "abs", "sign",
+];
+
+impl PropertySyntax {
+    /// Parse a syntax string such as `<length> | auto` or `<color>#`.
+    pub fn parse(syntax: &str) -> Option<Self> {
+        let syntax = syntax.trim();
+        if syntax == "*" {
+            return Some(Self::Universal);
+        }
+
+        let mut components = Vec::new();
+        for part in syntax.split('|') {
+            let part = part.trim();
+            let (body, multiplier) = match part.chars().last()? {
+                '+' => (&part[..part.len() - 1], Some(Multiplier::Space)),
+                '#' => (&part[..part.len() - 1], Some(Multiplier::Comma)),
+                _ => (part, None),
+            };
+            let kind = if let Some(name) = body.strip_prefix('<') {
+                ComponentKind::DataType(name.strip_suffix('>')?.trim().to_string())
+            } else if !body.is_empty() && body.chars().all(|c| c.is_alphanumeric() || c == '-') {
+                ComponentKind::Keyword(body.to_string())
+            } else {
+                return None;
+            };
+            components.push(SyntaxComponent { kind, multiplier });
+        }
+        Some(Self::Alternatives(components))
+    }
+
+    /// Check whether a declaration value matches this syntax. Values that
+    /// depend on `var()` cannot be checked statically and always match.
+    pub fn matches(&self, value: &str) -> bool {
+        let components = match self {
+            Self::Universal => return true,
+            Self::Alternatives(components) => components,
+        };
+
+        let value = value.trim();
+        let lower = value.to_ascii_lowercase();
+        if CSS_WIDE_KEYWORDS.contains(&lower.as_str()) || lower.contains("var(") {
+            return true;
+        }
+
+        let tokens: Vec<Token> = tokenize(value);
+        let groups = split_component_values(&tokens);
+        components
+            .iter()
+            .any(|component| component.matches(value, &tokens, &groups))
+    }
+}
+
+impl std::fmt::Display for PropertySyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Universal => write!(f, "*"),
+            Self::Alternatives(components) => {
+                let parts: Vec<String> = components
+                    .iter()
+                    .map(|c| {
+                        let body = match &c.kind {
+                            ComponentKind::DataType(name) => format!("<{}>", name),
+                            ComponentKind::Keyword(keyword) => keyword.clone(),
+                        };
+                        match c.multiplier {
+                            Some(Multiplier::Space) => format!("{}+", body),
+                            Some(Multiplier::Comma) => format!("{}#", body),
+                            None => body,
+                        }
+                    })
+                    .collect();
+                write!(f, "{}", parts.join(" | "))
+            }
+        }
+    }
+}
+
+/// A top-level component value: a token, or a function/block with its contents
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ComponentValue {
+    /// Index of the first token
+    pub(crate) first: usize,
+    /// Index of the last token (the closing parenthesis for functions)
+    pub(crate) last: usize,
+}
+
+/// Group tokens into top-level component values, skipping whitespace.
+pub(crate) fn split_component_values(tokens: &[Token]) -> Vec<ComponentValue> {
+    let mut groups = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let first = i;
+        if matches!(
+            tokens[i].kind,
+            TokenKind::Function | TokenKind::LeftParen | TokenKind::LeftBracket
+        ) {
+            let mut depth = 0usize;
+            while i < tokens.len() {
+                match tokens[i].kind {
+                    TokenKind::Function | TokenKind::LeftParen | TokenKind::LeftBracket => {
+                        depth += 1
+                    }
+                    TokenKind::RightParen | TokenKind::RightBracket => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+        }
+        groups.push(ComponentValue {
+            first,
+            last: i.min(tokens.len() - 1),
+        });
+        i += 1;
+    }
+    groups
+}
+
+impl SyntaxComponent {
+    fn matches(&self, value: &str, tokens: &[Token], groups: &[ComponentValue]) -> bool {
+        if groups.is_empty() {
+            return false;
+        }
+        let single = |group: &ComponentValue| self.matches_single(value, tokens, group);
+
+        match self.multiplier {
+            None => {
+                if let ComponentKind::DataType(name) = &self.kind {
+                    if name == "transform-list" {
+                        return groups
+                            .iter()
+                            .all(|g| is_transform_function(value, tokens, g));
+                    }
+                }
+                groups.len() == 1 && single(&groups[0])
+            }
+            Some(Multiplier::Space) => groups
+                .iter()
+                .all(|g| tokens[g.first].kind != TokenKind::Comma && single(g)),
+            Some(Multiplier::Comma) => {
+                // Alternate value, comma, value, ...
+                groups.len() % 2 == 1
+                    && groups.iter().enumerate().all(|(idx, g)| {
+                        if idx % 2 == 1 {
+                            tokens[g.first].kind == TokenKind::Comma
+                        } else {
+                            single(g)
+                        }
+                    })
+            }
+        }
+    }
+
+    fn matches_single(&self, value: &str, tokens: &[Token], group: &ComponentValue) -> bool {
+        let token = tokens[group.first];
+        let text = &value[token.start..tokens[group.last].end];
+        match &self.kind {
+            ComponentKind::Keyword(keyword) => {
+                token.kind == TokenKind::Ident && text.eq_ignore_ascii_case(keyword)
+            }
+            ComponentKind::DataType(name) => match_data_type(name, value, tokens, group),
+        }
+    }
+}
+
+fn is_math_function(value: &str, token: &Token) -> bool {
+    token
+        .function_name(value)
+        .is_some_and(|name| MATH_FUNCTIONS.contains(&name.to_ascii_lowercase().as_str()))
+}
+
+fn dimension_unit<'a>(value: &'a str, token: &Token) -> Option<String> {
+    if token.kind != TokenKind::Dimension {
+        return None;
+    }
+    let text: &'a str = token.text(value);
+    let unit_start = text
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| c.is_alphabetic() || *c == '\\' || *c == '_')
+        .map(|(idx, _)| idx)?;
+    Some(text[unit_start..].to_ascii_lowercase())
+}
+
+fn is_zero(value: &str, token: &Token) -> bool {
+    token.kind == TokenKind::Number && token.text(value).parse::<f64>().ok() == Some(0.0)
+}
+
+fn is_transform_function(value: &str, tokens: &[Token], group: &ComponentValue) -> bool {
+    const TRANSFORMS: [&str; 21] = [
+        "matrix",
+        "matrix3d",
+        "translate",
+        "translatex",
+        "translatey",
+        "translatez",
+        "translate3d",
+        "scale",
+        "scalex",
+        "scaley",
+        "scalez",
+        "scale3d",
+        "rotate",
+        "rotatex",
+        "rotatey",
+        "rotatez",
+        "rotate3d",
+        "skew",
+        "skewx",
+        "skewy",
+        "perspective",
+    ];
+    tokens[group.first]
+        .function_name(value)
+        .is_some_and(|name| TRANSFORMS.contains(&name.to_ascii_lowercase().as_str()))
+}
+
+fn match_data_type(name: &str, value: &str, tokens: &[Token], group: &ComponentValue) -> bool {
+    const LENGTH_UNITS: [&str; 41] = [
+        "px", "em", "rem", "ex", "rex", "ch", "rch", "ic", "ric", "cap", "rcap", "lh", "rlh", "vw",
+        "vh", "vi", "vb", "vmin", "vmax", "svw", "svh", "lvw", "lvh", "dvw", "dvh", "cqw", "cqh",
+        "cqi", "cqb", "cqmin", "cqmax", "cm", "mm", "q", "in", "pt", "pc", "svmin", "svmax",
+        "dvmin", "dvmax",
+    ];
+    const ANGLE_UNITS: [&str; 4] = ["deg", "grad", "rad", "turn"];
+    const TIME_UNITS: [&str; 2] = ["s", "ms"];
+    const RESOLUTION_UNITS: [&str; 4] = ["dpi", "dpcm", "dppx", "x"];
+
+    let token = tokens[group.first];
+    let text = &value[token.start..tokens[group.last].end];
+    let unit = dimension_unit(value, &token);
+    let has_unit = |units: &[&str]| unit.as_deref().is_some_and(|u| units.contains(&u));
+    let math = is_math_function(value, &token);
+
+    match name {
+        "length" => has_unit(&LENGTH_UNITS) || is_zero(value, &token) || math,
+        "percentage" => token.kind == TokenKind::Percentage || math,
+        "length-percentage" => {
+            has_unit(&LENGTH_UNITS)
+                || is_zero(value, &token)
+                || token.kind == TokenKind::Percentage
+                || math
+        }
+        "number" => token.kind == TokenKind::Number || math,
+        "integer" => {
+            (token.kind == TokenKind::Number && !token.text(value).contains(['.', 'e', 'E']))
+                || math
+        }
+        "angle" => has_unit(&ANGLE_UNITS) || is_zero(value, &token) || math,
+        "time" => has_unit(&TIME_UNITS) || math,
+        "resolution" => has_unit(&RESOLUTION_UNITS) || math,
+        "color" => {
+            parse_color(text).is_some()
+                || ["currentcolor", "transparent"].contains(&text.to_ascii_lowercase().as_str())
+                || token.function_name(value).is_some_and(|f| {
+                    let f = f.to_ascii_lowercase();
+                    [
+                        "rgb",
+                        "rgba",
+                        "hsl",
+                        "hsla",
+                        "hwb",
+                        "lab",
+                        "lch",
+                        "oklab",
+                        "oklch",
+                        "color",
+                        "color-mix",
+                        "light-dark",
+                    ]
+                    .contains(&f.as_str())
+                })
+        }
+        "url" => {
+            token.kind == TokenKind::Url
+                || token
+                    .function_name(value)
+                    .is_some_and(|f| f.eq_ignore_ascii_case("url"))
+        }
+        "image" => {
+            token.kind == TokenKind::Url
+                || token.function_name(value).is_some_and(|f| {
+                    let f = f.to_ascii_lowercase();
+                    f == "url"
+                        || f.ends_with("gradient")
+                        || ["image", "image-set", "cross-fade", "element", "paint"]
+                            .contains(&f.as_str())
+                })
+        }
+        "custom-ident" => {
+            token.kind == TokenKind::Ident
+                && !CSS_WIDE_KEYWORDS.contains(&text.to_ascii_lowercase().as_str())
+        }
+        "string" => token.kind == TokenKind::String,
+        "transform-function" => is_transform_function(value, tokens, group),
+        // Unknown data types cannot be checked; avoid false positives.
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_syntax_strings() {
+        assert_eq!(PropertySyntax::parse("*"), Some(PropertySyntax::Universal));
+        let syntax = PropertySyntax::parse("<length> | auto").unwrap();
+        assert_eq!(syntax.to_string(), "<length> | auto");
+        assert_eq!(
+            PropertySyntax::parse("<color>#").unwrap().to_string(),
+            "<color>#"
+        );
+        assert!(PropertySyntax::parse("<length").is_none());
+    }
+
+    #[test]
+    fn matches_single_data_types() {
+        let angle = PropertySyntax::parse("<angle>").unwrap();
+        assert!(angle.matches("45deg"));
+        assert!(angle.matches("0"));
+        assert!(angle.matches("calc(1turn / 4)"));
+        assert!(!angle.matches("10px"));
+        assert!(!angle.matches("10deg 20deg"));
+
+        let color = PropertySyntax::parse("<color>").unwrap();
+        assert!(color.matches("#3b82f6"));
+        assert!(color.matches("oklch(70% 0.1 200)"));
+        assert!(!color.matches("12px"));
+
+        let integer = PropertySyntax::parse("<integer>").unwrap();
+        assert!(integer.matches("3"));
+        assert!(!integer.matches("3.5"));
+    }
+
+    #[test]
+    fn matches_keywords_and_multipliers() {
+        let syntax = PropertySyntax::parse("<length>+ | auto").unwrap();
+        assert!(syntax.matches("auto"));
+        assert!(syntax.matches("1px 2rem"));
+        assert!(!syntax.matches("1px, 2rem"));
+
+        let list = PropertySyntax::parse("<color>#").unwrap();
+        assert!(list.matches("red, #fff, rgb(0 0 0)"));
+        assert!(!list.matches("red #fff"));
+    }
+
+    #[test]
+    fn skips_values_that_cannot_be_checked() {
+        let angle = PropertySyntax::parse("<angle>").unwrap();
+        assert!(angle.matches("var(--other)"));
+        assert!(angle.matches("inherit"));
+    }
+}
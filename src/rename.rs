@@ -0,0 +1,127 @@
+/// Check that `name` is a valid custom property name as it would be written
+/// in a stylesheet: `--` followed by ident code points (ASCII letters,
+/// digits, `-`, `_` or any non-ASCII character) or CSS escapes such as
+/// `\.` and `\e9 `.
+pub fn validate_custom_property_name(name: &str) -> Result<(), String> {
+    let rest = match name.strip_prefix("--") {
+        Some(rest) => rest,
+        None => {
+            return Err(format!(
+                "'{}' is not a custom property name: names must start with '--' (e.g. '--{}')",
+                name,
+                name.trim_start_matches('-')
+            ))
+        }
+    };
+    if rest.is_empty() {
+        return Err("'--' is reserved and cannot be used as a custom property name".to_string());
+    }
+
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        if is_ident_code_point(c) {
+            continue;
+        }
+        if c != '\\' {
+            return Err(format!(
+                "'{}' is not a valid custom property name: '{}' must be escaped as '\\{}'",
+                name, c, c
+            ));
+        }
+        match chars.next() {
+            None | Some('\n' | '\r' | '\x0c') => {
+                return Err(format!(
+                    "'{}' is not a valid custom property name: '\\' must be followed by the escaped character",
+                    name
+                ))
+            }
+            Some(c) if c.is_ascii_hexdigit() => {
+                for _ in 0..5 {
+                    if chars.next_if(|c| c.is_ascii_hexdigit()).is_none() {
+                        break;
+                    }
+                }
+                chars.next_if(|c| c.is_whitespace());
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// The name with CSS escapes decoded, so `--caf\e9` and `--café` compare
+/// equal. Invalid code points become U+FFFD, as in the tokenizer spec.
+pub fn unescape_identifier(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let Some(next) = chars.next() else {
+            out.push('\u{FFFD}');
+            break;
+        };
+        if !next.is_ascii_hexdigit() {
+            out.push(next);
+            continue;
+        }
+        let mut hex = next.to_string();
+        while hex.len() < 6 {
+            match chars.next_if(|c| c.is_ascii_hexdigit()) {
+                Some(digit) => hex.push(digit),
+                None => break,
+            }
+        }
+        chars.next_if(|c| c.is_whitespace());
+        let code_point = u32::from_str_radix(&hex, 16).unwrap_or(0);
+        out.push(
+            char::from_u32(code_point)
+                .filter(|c| *c != '\0')
+                .unwrap_or('\u{FFFD}'),
+        );
+    }
+    out
+}
+
+fn is_ident_code_point(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_custom_property_names() {
+        assert!(validate_custom_property_name("--primary-color").is_ok());
+        assert!(validate_custom_property_name("--_private").is_ok());
+        assert!(validate_custom_property_name("--café").is_ok());
+        assert!(validate_custom_property_name("--色").is_ok());
+        assert!(validate_custom_property_name("--space\\.5").is_ok());
+        assert!(validate_custom_property_name("--caf\\e9 ").is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        let missing_dashes = validate_custom_property_name("primary").unwrap_err();
+        assert!(missing_dashes.contains("'--primary'"));
+        assert!(validate_custom_property_name("-primary").is_err());
+        assert!(validate_custom_property_name("--").is_err());
+        assert!(validate_custom_property_name("--a b")
+            .unwrap_err()
+            .contains("'\\ '"));
+        assert!(validate_custom_property_name("--a:b").is_err());
+        assert!(validate_custom_property_name("--trailing\\").is_err());
+    }
+
+    #[test]
+    fn unescapes_identifiers() {
+        assert_eq!(unescape_identifier("--caf\\e9"), "--café");
+        assert_eq!(unescape_identifier("--caf\\0000e9 x"), "--caféx");
+        assert_eq!(unescape_identifier("--space\\.5"), "--space.5");
+        assert_eq!(unescape_identifier("--bad\\0"), "--bad\u{FFFD}");
+        assert_eq!(unescape_identifier("--plain"), "--plain");
+    }
+}
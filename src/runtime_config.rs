@@ -1,5 +1,8 @@
 use std::collections::HashMap;
 
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PathDisplayMode {
     Relative,
@@ -7,6 +10,40 @@ pub enum PathDisplayMode {
     Abbreviated,
 }
 
+/// What rename does when the new name is already defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenameCollision {
+    /// Rename anyway and show a warning
+    Warn,
+    /// Reject the rename with an error
+    Refuse,
+}
+
+/// Severity reported for a diagnostic rule, or `Off` to disable it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+    Information,
+    Hint,
+    Off,
+}
+
+impl DiagnosticLevel {
+    /// The LSP severity, or `None` when the rule is turned off
+    pub fn severity(self) -> Option<DiagnosticSeverity> {
+        match self {
+            DiagnosticLevel::Error => Some(DiagnosticSeverity::ERROR),
+            DiagnosticLevel::Warning => Some(DiagnosticSeverity::WARNING),
+            DiagnosticLevel::Information => Some(DiagnosticSeverity::INFORMATION),
+            DiagnosticLevel::Hint => Some(DiagnosticSeverity::HINT),
+            DiagnosticLevel::Off => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RuntimeConfig {
     pub enable_color_provider: bool,
@@ -15,6 +52,14 @@ pub struct RuntimeConfig {
     pub ignore_globs: Option<Vec<String>>,
     pub path_display_mode: PathDisplayMode,
     pub path_display_abbrev_length: usize,
+    pub tokens_file: Option<String>,
+    pub token_suggestions: bool,
+    pub unused_variables: bool,
+    pub unused_allowlist: Option<Vec<String>>,
+    pub inlay_hint_max_length: usize,
+    pub inlay_hints_non_color_only: bool,
+    pub rename_collision: RenameCollision,
+    pub diagnostic_severity: HashMap<String, DiagnosticLevel>,
 }
 
 fn get_arg_value(args: &[String], name: &str) -> Option<String> {
@@ -59,6 +104,37 @@ fn normalize_path_display_mode(value: Option<&str>) -> Option<PathDisplayMode> {
     }
 }
 
+fn parse_rename_collision(value: Option<&str>) -> Option<RenameCollision> {
+    match value?.trim().to_lowercase().as_str() {
+        "warn" => Some(RenameCollision::Warn),
+        "refuse" | "error" => Some(RenameCollision::Refuse),
+        _ => None,
+    }
+}
+
+fn parse_diagnostic_level(value: &str) -> Option<DiagnosticLevel> {
+    match value.trim().to_lowercase().as_str() {
+        "error" => Some(DiagnosticLevel::Error),
+        "warning" | "warn" => Some(DiagnosticLevel::Warning),
+        "information" | "info" => Some(DiagnosticLevel::Information),
+        "hint" => Some(DiagnosticLevel::Hint),
+        "off" | "none" => Some(DiagnosticLevel::Off),
+        _ => None,
+    }
+}
+
+/// Parse `rule=level` pairs such as `undefined-variable=error,unused-variable=off`,
+/// skipping entries with an unknown level.
+fn parse_diagnostic_severities(value: &str) -> HashMap<String, DiagnosticLevel> {
+    split_lookup_list(value)
+        .iter()
+        .filter_map(|entry| {
+            let (rule, level) = entry.split_once('=')?;
+            Some((rule.trim().to_string(), parse_diagnostic_level(level)?))
+        })
+        .collect()
+}
+
 fn parse_path_display(value: Option<&str>) -> (Option<PathDisplayMode>, Option<i64>) {
     let raw = match value {
         Some(v) if !v.trim().is_empty() => v,
@@ -177,6 +253,47 @@ pub fn build_runtime_config_with_env(
             .map(|v| v == "1")
             .unwrap_or(false);
 
+    let token_suggestions = !args.iter().any(|arg| arg == "--no-token-suggestions")
+        && env
+            .get("CSS_LSP_TOKEN_SUGGESTIONS")
+            .map(|v| v != "0")
+            .unwrap_or(true);
+
+    let unused_variables = args.iter().any(|arg| arg == "--unused-variables")
+        || env
+            .get("CSS_LSP_UNUSED_VARIABLES")
+            .map(|v| v == "1")
+            .unwrap_or(false);
+    let unused_allowlist = get_arg_value(args, "unused-allowlist")
+        .or_else(|| env.get("CSS_LSP_UNUSED_ALLOWLIST").cloned())
+        .map(|value| split_lookup_list(&value))
+        .filter(|patterns| !patterns.is_empty());
+
+    let inlay_hint_max_length = parse_optional_int(
+        get_arg_value(args, "inlay-hint-max-length")
+            .or_else(|| env.get("CSS_LSP_INLAY_HINT_MAX_LENGTH").cloned())
+            .as_deref(),
+    )
+    .unwrap_or(30)
+    .max(0) as usize;
+    let inlay_hints_non_color_only = args.iter().any(|arg| arg == "--inlay-hints-non-color-only")
+        || env
+            .get("CSS_LSP_INLAY_HINTS_NON_COLOR_ONLY")
+            .map(|v| v == "1")
+            .unwrap_or(false);
+
+    let rename_collision = parse_rename_collision(
+        get_arg_value(args, "rename-collision")
+            .or_else(|| env.get("CSS_LSP_RENAME_COLLISION").cloned())
+            .as_deref(),
+    )
+    .unwrap_or(RenameCollision::Warn);
+
+    let diagnostic_severity = get_arg_value(args, "diagnostic-severity")
+        .or_else(|| env.get("CSS_LSP_DIAGNOSTIC_SEVERITY").cloned())
+        .map(|value| parse_diagnostic_severities(&value))
+        .unwrap_or_default();
+
     let lookup_files = resolve_lookup_files(args, env);
     let ignore_globs = resolve_ignore_globs(args, env);
 
@@ -194,6 +311,10 @@ pub fn build_runtime_config_with_env(
         .unwrap_or(1);
     let path_display_abbrev_length = length_raw.max(0) as usize;
 
+    let tokens_file = get_arg_value(args, "tokens-file")
+        .or_else(|| env.get("CSS_LSP_TOKENS_FILE").cloned())
+        .filter(|path| !path.trim().is_empty());
+
     RuntimeConfig {
         enable_color_provider,
         color_only_on_variables,
@@ -201,6 +322,14 @@ pub fn build_runtime_config_with_env(
         ignore_globs,
         path_display_mode,
         path_display_abbrev_length,
+        tokens_file,
+        token_suggestions,
+        unused_variables,
+        unused_allowlist,
+        inlay_hint_max_length,
+        inlay_hints_non_color_only,
+        rename_collision,
+        diagnostic_severity,
     }
 }
 
@@ -222,6 +351,16 @@ mod tests {
             "a.css,b.html".to_string(),
             "--ignore-glob=dist/**".to_string(),
             "--path-display=abbreviated:2".to_string(),
+            "--tokens-file=styles/tokens.css".to_string(),
+            "--no-token-suggestions".to_string(),
+            "--unused-variables".to_string(),
+            "--unused-allowlist=brand-*,public-*".to_string(),
+            "--inlay-hint-max-length".to_string(),
+            "12".to_string(),
+            "--inlay-hints-non-color-only".to_string(),
+            "--rename-collision=refuse".to_string(),
+            "--diagnostic-severity".to_string(),
+            "undefined-variable=error, unused-variable=off,circular-reference=loud".to_string(),
         ];
         let mut env = HashMap::new();
         env.insert(
@@ -230,6 +369,15 @@ mod tests {
         );
         env.insert("CSS_LSP_IGNORE_GLOBS".to_string(), "ignored/**".to_string());
         env.insert("CSS_LSP_PATH_DISPLAY".to_string(), "absolute".to_string());
+        env.insert("CSS_LSP_TOKENS_FILE".to_string(), "ignored.css".to_string());
+        env.insert(
+            "CSS_LSP_INLAY_HINT_MAX_LENGTH".to_string(),
+            "50".to_string(),
+        );
+        env.insert(
+            "CSS_LSP_DIAGNOSTIC_SEVERITY".to_string(),
+            "hardcoded-token-value=warning".to_string(),
+        );
 
         let config = build_runtime_config_with_env(&args, &env);
 
@@ -245,6 +393,23 @@ mod tests {
         );
         assert_eq!(config.path_display_mode, PathDisplayMode::Abbreviated);
         assert_eq!(config.path_display_abbrev_length, 2);
+        assert_eq!(config.tokens_file.as_deref(), Some("styles/tokens.css"));
+        assert!(!config.token_suggestions);
+        assert!(config.unused_variables);
+        assert_eq!(
+            config.unused_allowlist.as_ref().unwrap(),
+            &vec!["brand-*".to_string(), "public-*".to_string()]
+        );
+        assert_eq!(config.inlay_hint_max_length, 12);
+        assert!(config.inlay_hints_non_color_only);
+        assert_eq!(config.rename_collision, RenameCollision::Refuse);
+        assert_eq!(
+            config.diagnostic_severity,
+            HashMap::from([
+                ("undefined-variable".to_string(), DiagnosticLevel::Error),
+                ("unused-variable".to_string(), DiagnosticLevel::Off),
+            ])
+        );
     }
 
     #[test]
@@ -261,6 +426,11 @@ mod tests {
         );
         env.insert("CSS_LSP_PATH_DISPLAY".to_string(), "relative".to_string());
         env.insert("CSS_LSP_PATH_DISPLAY_LENGTH".to_string(), "3".to_string());
+        env.insert("CSS_LSP_RENAME_COLLISION".to_string(), "error".to_string());
+        env.insert(
+            "CSS_LSP_DIAGNOSTIC_SEVERITY".to_string(),
+            "undefined-variable-with-fallback=hint".to_string(),
+        );
 
         let config = build_runtime_config_with_env(&args, &env);
 
@@ -276,5 +446,42 @@ mod tests {
         );
         assert_eq!(config.path_display_mode, PathDisplayMode::Relative);
         assert_eq!(config.path_display_abbrev_length, 3);
+        assert!(config.tokens_file.is_none());
+        assert!(config.token_suggestions);
+        assert!(!config.unused_variables);
+        assert!(config.unused_allowlist.is_none());
+        assert_eq!(config.inlay_hint_max_length, 30);
+        assert!(!config.inlay_hints_non_color_only);
+        assert_eq!(config.rename_collision, RenameCollision::Refuse);
+        assert_eq!(
+            config
+                .diagnostic_severity
+                .get("undefined-variable-with-fallback"),
+            Some(&DiagnosticLevel::Hint)
+        );
+        assert_eq!(DiagnosticLevel::Off.severity(), None);
+    }
+
+    #[test]
+    fn config_applies_diagnostic_severity_overrides() {
+        let args =
+            vec!["--diagnostic-severity=undefined-variable=error,unused-variable=off".to_string()];
+        let config = crate::types::Config::from_runtime(&build_runtime_config_with_env(
+            &args,
+            &HashMap::new(),
+        ));
+
+        assert_eq!(
+            config.diagnostic_severity("undefined-variable", DiagnosticSeverity::WARNING),
+            Some(DiagnosticSeverity::ERROR)
+        );
+        assert_eq!(
+            config.diagnostic_severity("unused-variable", DiagnosticSeverity::HINT),
+            None
+        );
+        assert_eq!(
+            config.diagnostic_severity("circular-reference", DiagnosticSeverity::ERROR),
+            Some(DiagnosticSeverity::ERROR)
+        );
     }
 }
@@ -0,0 +1,148 @@
+use tower_lsp::lsp_types::{
+    Position, Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensLegend,
+    Url,
+};
+
+/// Modifier for a `var()` whose variable has no definition
+pub const UNDEFINED_MODIFIER: &str = "undefined";
+
+/// Bit of each modifier in a token's modifier set, in legend order
+pub const DECLARATION: u32 = 1 << 0;
+pub const DEPRECATED: u32 = 1 << 1;
+pub const READONLY: u32 = 1 << 2;
+pub const DEFAULT_LIBRARY: u32 = 1 << 3;
+pub const UNDEFINED: u32 = 1 << 4;
+
+/// Legend advertised in the server capabilities: every custom property name
+/// is a `variable`, and definitions carry the `declaration` modifier.
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: vec![SemanticTokenType::VARIABLE],
+        token_modifiers: vec![
+            SemanticTokenModifier::DECLARATION,
+            SemanticTokenModifier::DEPRECATED,
+            SemanticTokenModifier::READONLY,
+            SemanticTokenModifier::DEFAULT_LIBRARY,
+            SemanticTokenModifier::new(UNDEFINED_MODIFIER),
+        ],
+    }
+}
+
+/// A custom property name in a document with its modifier bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClassifiedName {
+    /// Range of the name (e.g., "--foo"), on a single line
+    pub range: Range,
+
+    /// Bitset of `DECLARATION`, `DEPRECATED`, ...
+    pub modifiers: u32,
+}
+
+/// Whether a document comes from a third-party package (e.g. `node_modules`)
+pub fn is_third_party(uri: &Url) -> bool {
+    uri.path_segments()
+        .is_some_and(|mut segments| segments.any(|segment| segment == "node_modules"))
+}
+
+/// Delta-encode names in document order, keeping only those overlapping
+/// `within` when given. Names at the same position are reported once.
+pub fn encode(mut names: Vec<ClassifiedName>, within: Option<Range>) -> Vec<SemanticToken> {
+    if let Some(within) = within {
+        names.retain(|name| name.range.end >= within.start && name.range.start <= within.end);
+    }
+    names.sort_by_key(|name| name.range.start);
+    names.dedup_by_key(|name| name.range.start);
+
+    let mut tokens = Vec::with_capacity(names.len());
+    let mut previous = Position::default();
+    for name in names {
+        let start = name.range.start;
+        if name.range.end.line != start.line || name.range.end.character <= start.character {
+            continue;
+        }
+        let delta_line = start.line - previous.line;
+        let delta_start = if delta_line == 0 {
+            start.character - previous.character
+        } else {
+            start.character
+        };
+        tokens.push(SemanticToken {
+            delta_line,
+            delta_start,
+            length: name.range.end.character - start.character,
+            token_type: 0,
+            token_modifiers_bitset: name.modifiers,
+        });
+        previous = start;
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(line: u32, start: u32, end: u32, modifiers: u32) -> ClassifiedName {
+        ClassifiedName {
+            range: Range::new(Position::new(line, start), Position::new(line, end)),
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn encodes_relative_positions_in_document_order() {
+        let tokens = encode(
+            vec![
+                name(2, 4, 9, UNDEFINED),
+                name(0, 8, 13, DECLARATION | READONLY),
+                name(0, 20, 25, 0),
+                name(0, 20, 25, 0),
+            ],
+            None,
+        );
+        let encoded: Vec<(u32, u32, u32, u32)> = tokens
+            .iter()
+            .map(|t| {
+                (
+                    t.delta_line,
+                    t.delta_start,
+                    t.length,
+                    t.token_modifiers_bitset,
+                )
+            })
+            .collect();
+        assert_eq!(
+            encoded,
+            vec![
+                (0, 8, 5, DECLARATION | READONLY),
+                (0, 12, 5, 0),
+                (2, 4, 5, UNDEFINED)
+            ]
+        );
+    }
+
+    #[test]
+    fn range_requests_keep_only_overlapping_names() {
+        let within = Range::new(Position::new(1, 0), Position::new(2, 0));
+        let tokens = encode(
+            vec![
+                name(0, 0, 5, 0),
+                name(1, 2, 7, DEPRECATED),
+                name(3, 0, 5, 0),
+            ],
+            Some(within),
+        );
+        assert_eq!(tokens.len(), 1);
+        assert_eq!((tokens[0].delta_line, tokens[0].delta_start), (1, 2));
+    }
+
+    #[test]
+    fn detects_third_party_documents() {
+        assert!(is_third_party(
+            &Url::parse("file:///app/node_modules/open-props/open-props.min.css").unwrap()
+        ));
+        assert!(!is_third_party(
+            &Url::parse("file:///app/src/tokens.css").unwrap()
+        ));
+    }
+}
@@ -1,7 +1,8 @@
 use regex::Regex;
 
 use crate::dom_tree::DomTree;
-use crate::types::{CssVariable, DOMNodeInfo};
+use crate::layers::LayerOrder;
+use crate::types::{CssVariable, CssVariableUsage, DOMNodeInfo};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Specificity {
@@ -26,11 +27,7 @@ pub fn calculate_specificity(selector: &str) -> Specificity {
         return Specificity::new(0, 0, 0);
     }
 
-    let selectors: Vec<&str> = selector
-        .split(',')
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .collect();
+    let selectors = split_selector_list(selector);
     if selectors.len() > 1 {
         let mut best = Specificity::new(0, 0, 0);
         for sel in selectors {
@@ -74,6 +71,37 @@ pub fn calculate_specificity(selector: &str) -> Specificity {
     Specificity::new(ids, classes + attrs + pseudo_classes, elements)
 }
 
+/// Split a selector list on top-level commas, ignoring commas inside
+/// parentheses, attribute selectors and strings.
+pub fn split_selector_list(selector: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+
+    for (idx, ch) in selector.char_indices() {
+        if let Some(q) = quote {
+            if ch == q {
+                quote = None;
+            }
+            continue;
+        }
+        match ch {
+            '"' | '\'' => quote = Some(ch),
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(selector[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(selector[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
 pub fn compare_specificity(a: Specificity, b: Specificity) -> i32 {
     if a.ids != b.ids {
         return if a.ids > b.ids { 1 } else { -1 };
@@ -126,9 +154,62 @@ pub fn matches_context(
     })
 }
 
+/// Definitions that can reach the element of an HTML usage: rule definitions
+/// whose selector matches the node or one of its ancestors (custom properties
+/// inherit), and inline definitions on those elements. Falls back to all
+/// definitions when the usage has no DOM node or nothing matches.
+pub fn filter_by_dom_match(
+    definitions: Vec<CssVariable>,
+    usage: &CssVariableUsage,
+    dom_tree: Option<&DomTree>,
+) -> Vec<CssVariable> {
+    let node_index = usage.dom_node.as_ref().and_then(|node| node.node_index);
+    let (Some(tree), Some(node_index)) = (dom_tree, node_index) else {
+        return definitions;
+    };
+    let elements: Vec<usize> =
+        std::iter::successors(Some(node_index), |&idx| tree.parent(idx)).collect();
+    let matching: Vec<CssVariable> = definitions
+        .iter()
+        .filter(|def| {
+            if def.inline {
+                def.uri == usage.uri
+                    && tree
+                        .find_node_at_position(def.source_position)
+                        .and_then(|node| node.node_index)
+                        .is_some_and(|idx| elements.contains(&idx))
+            } else {
+                elements
+                    .iter()
+                    .any(|&idx| tree.matches_selector(idx, &def.selector))
+            }
+        })
+        .cloned()
+        .collect();
+    if matching.is_empty() {
+        definitions
+    } else {
+        matching
+    }
+}
+
+/// Whether a definition only applies under an `@media`, `@supports`,
+/// `@container` or `@scope` condition.
+pub fn is_conditional(variable: &CssVariable) -> bool {
+    variable.conditions.iter().any(|c| c.is_conditional())
+}
+
 /// Sort variables by cascade rules (winner first):
-/// !important > inline > specificity > source order (later wins)
+/// !important > inline > layer > unconditional > specificity > source order (later wins)
+///
+/// Whether a condition holds is unknown statically, so unconditional
+/// definitions are treated as the baseline and rank ahead of conditional ones.
 pub fn sort_by_cascade(variables: &mut [CssVariable]) {
+    sort_by_cascade_with_layers(variables, &LayerOrder::default());
+}
+
+/// Sort variables by cascade rules using the workspace layer order.
+pub fn sort_by_cascade_with_layers(variables: &mut [CssVariable], layers: &LayerOrder) {
     variables.sort_by(|a, b| {
         if a.important != b.important {
             return if a.important {
@@ -146,6 +227,21 @@ pub fn sort_by_cascade(variables: &mut [CssVariable]) {
             };
         }
 
+        let layer_cmp = layers.compare(a, b);
+        if layer_cmp != std::cmp::Ordering::Equal {
+            return layer_cmp;
+        }
+
+        let conditional_a = is_conditional(a);
+        let conditional_b = is_conditional(b);
+        if conditional_a != conditional_b {
+            return if conditional_b {
+                std::cmp::Ordering::Less
+            } else {
+                std::cmp::Ordering::Greater
+            };
+        }
+
         let spec_a = calculate_specificity(&a.selector);
         let spec_b = calculate_specificity(&b.selector);
         let spec_cmp = compare_specificity(spec_a, spec_b);
@@ -164,6 +260,7 @@ pub fn sort_by_cascade(variables: &mut [CssVariable]) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::AtRuleCondition;
 
     #[test]
     fn basic_specificity_calculation() {
@@ -219,6 +316,58 @@ mod tests {
         assert_eq!(compare_specificity(root, root), 0);
     }
 
+    #[test]
+    fn selector_list_splitting_respects_nesting() {
+        assert_eq!(
+            split_selector_list(".a, :is(.b, .c) > [data-x=\"1,2\"]"),
+            vec![".a", ":is(.b, .c) > [data-x=\"1,2\"]"]
+        );
+        let spec = calculate_specificity(".a, #b .c");
+        assert_eq!(format_specificity(spec), "(1,1,0)");
+    }
+
+    fn variable(value: &str, selector: &str, source_position: usize) -> CssVariable {
+        CssVariable {
+            name: "--bg".to_string(),
+            value: value.to_string(),
+            uri: tower_lsp::lsp_types::Url::parse("file:///test.css").unwrap(),
+            range: Default::default(),
+            name_range: None,
+            value_range: None,
+            selector: selector.to_string(),
+            conditions: Vec::new(),
+            layer: None,
+            important: false,
+            inline: false,
+            deprecated: false,
+            source_position,
+        }
+    }
+
+    #[test]
+    fn cascade_prefers_unconditional_definitions() {
+        let base = variable("#fff", ":root", 0);
+        let mut dark = variable("#111", ":root", 10);
+        dark.conditions.push(AtRuleCondition::new(
+            "media",
+            "(prefers-color-scheme: dark)",
+        ));
+        let mut layered = variable("#eee", ":root", 20);
+        layered
+            .conditions
+            .push(AtRuleCondition::new("layer", "tokens"));
+        layered.layer = Some("tokens".to_string());
+
+        let mut vars = vec![dark.clone(), base.clone()];
+        sort_by_cascade(&mut vars);
+        assert_eq!(vars[0].value, "#fff");
+
+        // Layers are not conditions, but unlayered styles beat layered ones.
+        let mut vars = vec![base, layered];
+        sort_by_cascade(&mut vars);
+        assert_eq!(vars[0].value, "#fff");
+    }
+
     #[test]
     fn context_matching_basics() {
         assert!(matches_context(":root", "div", None, None));
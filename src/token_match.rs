@@ -0,0 +1,215 @@
+use std::collections::BTreeSet;
+use std::ops;
+
+use tower_lsp::lsp_types::Color;
+
+use crate::code_actions::declaration_values;
+use crate::color::parse_color;
+use crate::parsers::stylesheet::serialize_tokens;
+use crate::parsers::tokenizer::{tokenize, TokenKind};
+use crate::property_syntax::split_component_values;
+use crate::types::CssVariable;
+
+/// Values too generic to suggest a token for (e.g. `margin: 0`)
+const IGNORED_VALUES: [&str; 10] = [
+    "0",
+    "auto",
+    "none",
+    "inherit",
+    "initial",
+    "unset",
+    "revert",
+    "revert-layer",
+    "currentcolor",
+    "transparent",
+];
+
+/// Largest per-channel difference for two colors to count as equal
+const COLOR_TOLERANCE: f32 = 0.5 / 255.0;
+
+/// Lookup from literal values to the variables defining them
+#[derive(Debug, Default)]
+pub struct TokenIndex {
+    /// Normalized value text -> variable names
+    values: Vec<(String, String)>,
+
+    /// Parsed color values -> variable names
+    colors: Vec<(Color, String)>,
+}
+
+/// A literal in a declaration value that equals one or more variables' values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HardcodedValue {
+    /// Byte span of the literal in the document
+    pub span: ops::Range<usize>,
+
+    /// Matching variable names, sorted
+    pub candidates: Vec<String>,
+}
+
+impl TokenIndex {
+    pub fn build(variables: &[CssVariable]) -> Self {
+        let mut index = Self::default();
+        for variable in variables {
+            let value = normalize_value(&variable.value);
+            if value.is_empty() || value.contains("var(") || is_ignored(&value) {
+                continue;
+            }
+            if let Some(color) = parse_color(&value) {
+                index.colors.push((color, variable.name.clone()));
+            }
+            index.values.push((value, variable.name.clone()));
+        }
+        index
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Variables whose value equals `literal`, comparing colors by value so
+    /// `#3B82F6` and `rgb(59, 130, 246)` match `#3b82f6`.
+    pub fn candidates(&self, literal: &str) -> Vec<String> {
+        let literal = normalize_value(literal);
+        if literal.is_empty() || is_ignored(&literal) {
+            return Vec::new();
+        }
+
+        let mut names: BTreeSet<String> = self
+            .values
+            .iter()
+            .filter(|(value, _)| *value == literal)
+            .map(|(_, name)| name.clone())
+            .collect();
+        if let Some(color) = parse_color(&literal) {
+            names.extend(
+                self.colors
+                    .iter()
+                    .filter(|(candidate, _)| colors_equal(candidate, &color))
+                    .map(|(_, name)| name.clone()),
+            );
+        }
+        names.into_iter().collect()
+    }
+}
+
+/// Literals in the document's declaration values that equal a variable's
+/// value: whole values, or individual colors inside longer values (e.g. the
+/// color in `border: 1px solid #3b82f6`). Custom property definitions are
+/// skipped since they are the tokens themselves.
+pub fn find_hardcoded_values(text: &str, is_html: bool, index: &TokenIndex) -> Vec<HardcodedValue> {
+    let mut found = Vec::new();
+    if index.is_empty() {
+        return found;
+    }
+
+    for value in declaration_values(text, is_html) {
+        if value.property.starts_with("--") {
+            continue;
+        }
+        let source = &text[value.span.clone()];
+        if source.contains("var(") {
+            continue;
+        }
+
+        let candidates = index.candidates(source);
+        if !candidates.is_empty() {
+            found.push(HardcodedValue {
+                span: value.span.clone(),
+                candidates,
+            });
+            continue;
+        }
+
+        let tokens = tokenize(source);
+        let groups = split_component_values(&tokens);
+        if groups.len() < 2 {
+            continue;
+        }
+        for group in groups {
+            let first = tokens[group.first];
+            let is_color_candidate = matches!(
+                first.kind,
+                TokenKind::Hash | TokenKind::Ident | TokenKind::Function
+            );
+            if !is_color_candidate {
+                continue;
+            }
+            let span = first.start..tokens[group.last].end;
+            if parse_color(&source[span.clone()]).is_none() {
+                continue;
+            }
+            let candidates = index.candidates(&source[span.clone()]);
+            if !candidates.is_empty() {
+                found.push(HardcodedValue {
+                    span: value.span.start + span.start..value.span.start + span.end,
+                    candidates,
+                });
+            }
+        }
+    }
+    found
+}
+
+fn normalize_value(value: &str) -> String {
+    serialize_tokens(value, &tokenize(value))
+}
+
+fn is_ignored(value: &str) -> bool {
+    IGNORED_VALUES.contains(&value.to_ascii_lowercase().as_str())
+}
+
+fn colors_equal(a: &Color, b: &Color) -> bool {
+    (a.red - b.red).abs() <= COLOR_TOLERANCE
+        && (a.green - b.green).abs() <= COLOR_TOLERANCE
+        && (a.blue - b.blue).abs() <= COLOR_TOLERANCE
+        && (a.alpha - b.alpha).abs() <= COLOR_TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_colors_in_any_notation() {
+        let index = TokenIndex::build(&[
+            CssVariable::for_test("--primary-color", "#3b82f6"),
+            CssVariable::for_test("--brand", "#3B82F6"),
+            CssVariable::for_test("--spacing", "1rem"),
+            CssVariable::for_test("--zero", "0"),
+        ]);
+        assert_eq!(
+            index.candidates("rgb(59,130,246)"),
+            vec!["--brand", "--primary-color"]
+        );
+        assert_eq!(index.candidates("1rem"), vec!["--spacing"]);
+        assert!(index.candidates("0").is_empty());
+        assert!(index.candidates("2rem").is_empty());
+    }
+
+    #[test]
+    fn finds_whole_values_and_colors_in_shorthands() {
+        let index = TokenIndex::build(&[
+            CssVariable::for_test("--primary-color", "#3b82f6"),
+            CssVariable::for_test("--spacing", "1rem"),
+        ]);
+        let text = ":root { --primary-color: #3b82f6; }\n\
+                    .a { padding: 1rem; border: 1px solid #3B82F6; margin: var(--spacing); }";
+        let found = find_hardcoded_values(text, false, &index);
+        let literals: Vec<&str> = found.iter().map(|f| &text[f.span.clone()]).collect();
+        assert_eq!(literals, vec!["1rem", "#3B82F6"]);
+        assert_eq!(found[1].candidates, vec!["--primary-color"]);
+    }
+
+    #[test]
+    fn skips_property_registrations() {
+        let index = TokenIndex::build(&[CssVariable::for_test("--primary-color", "#3b82f6")]);
+        let text =
+            "@property --accent { syntax: '<color>'; inherits: true; initial-value: #3b82f6; }\n\
+                    @media (min-width: 40em) { .a { color: #3b82f6; } }";
+        let found = find_hardcoded_values(text, false, &index);
+        let literals: Vec<&str> = found.iter().map(|f| &text[f.span.clone()]).collect();
+        assert_eq!(literals, vec!["#3b82f6"]);
+        assert!(found[0].span.start > text.find("@media").unwrap());
+    }
+}
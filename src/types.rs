@@ -1,7 +1,9 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
-use tower_lsp::lsp_types::{Position, Range, Url};
+use tower_lsp::lsp_types::{DiagnosticSeverity, Position, Range, Url};
 
-use crate::runtime_config::RuntimeConfig;
+use crate::runtime_config::{DiagnosticLevel, RenameCollision, RuntimeConfig};
 
 /// Represents a CSS variable definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,16 +29,47 @@ pub struct CssVariable {
     /// CSS selector where this variable is defined (e.g., ":root", "div", ".class")
     pub selector: String,
 
+    /// Enclosing at-rules, outermost first (e.g., `@media (min-width: 768px)`)
+    pub conditions: Vec<AtRuleCondition>,
+
+    /// Cascade layer declared in this document (full dotted name, e.g., "components.buttons")
+    pub layer: Option<String>,
+
     /// Whether this definition uses !important
     pub important: bool,
 
     /// Whether this definition is from an inline style attribute
     pub inline: bool,
 
+    /// Whether a comment directly before the declaration contains `@deprecated`
+    pub deprecated: bool,
+
     /// Character position in file (for source order in cascade)
     pub source_position: usize,
 }
 
+#[cfg(test)]
+impl CssVariable {
+    /// An unlayered `:root` definition in `file:///test.css`, for tests
+    pub fn for_test(name: &str, value: &str) -> Self {
+        CssVariable {
+            name: name.to_string(),
+            value: value.to_string(),
+            uri: Url::parse("file:///test.css").unwrap(),
+            range: Range::default(),
+            name_range: None,
+            value_range: None,
+            selector: ":root".to_string(),
+            conditions: Vec::new(),
+            layer: None,
+            important: false,
+            inline: false,
+            deprecated: false,
+            source_position: 0,
+        }
+    }
+}
+
 /// Represents a CSS variable usage (var() call)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CssVariableUsage {
@@ -52,13 +85,169 @@ pub struct CssVariableUsage {
     /// Range of just the variable name in var()
     pub name_range: Option<Range>,
 
+    /// Fallback after the first comma (e.g., `#ccc` in `var(--bg, #ccc)`)
+    pub fallback: Option<VarFallback>,
+
     /// CSS selector context where variable is used
     pub usage_context: String,
 
+    /// Enclosing at-rules, outermost first
+    pub conditions: Vec<AtRuleCondition>,
+
     /// DOM node info if usage is in HTML (for inline styles)
     pub dom_node: Option<DOMNodeInfo>,
 }
 
+/// The fallback of a `var()` call, used when the variable is undefined
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VarFallback {
+    /// Fallback text without surrounding whitespace (e.g., "var(--b, #ccc)")
+    pub text: String,
+
+    /// Range of the fallback text
+    pub range: Range,
+}
+
+/// A value with every `var()` substituted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedValue {
+    /// Fully substituted value (e.g., "calc(0.25rem * 4)")
+    pub value: String,
+
+    /// Variables walked during substitution, in order of first visit
+    pub chain: Vec<String>,
+
+    /// False when a reference was undefined without a fallback or cyclic
+    pub complete: bool,
+}
+
+/// Represents an `@property` registration of a custom property
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisteredProperty {
+    /// Registered property name (e.g., "--angle")
+    pub name: String,
+
+    /// Document URI where the property is registered
+    pub uri: Url,
+
+    /// Range of the entire `@property` rule
+    pub range: Range,
+
+    /// Range of the property name in the prelude
+    pub name_range: Range,
+
+    /// `syntax` descriptor without quotes (e.g., "<angle>")
+    pub syntax: Option<String>,
+
+    /// `inherits` descriptor
+    pub inherits: Option<bool>,
+
+    /// `initial-value` descriptor
+    pub initial_value: Option<String>,
+}
+
+/// Kind of an enclosing at-rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AtRuleKind {
+    Media,
+    Supports,
+    Container,
+    Layer,
+    Scope,
+    Other,
+}
+
+/// An at-rule enclosing a definition or usage (e.g., `@media (prefers-color-scheme: dark)`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtRuleCondition {
+    pub kind: AtRuleKind,
+
+    /// Lowercased at-rule name without the `@` (e.g., "media")
+    pub name: String,
+
+    /// Prelude text (e.g., "(prefers-color-scheme: dark)")
+    pub params: String,
+}
+
+impl AtRuleCondition {
+    pub fn new(name: &str, params: &str) -> Self {
+        let name = name.to_lowercase();
+        let kind = match name.as_str() {
+            "media" => AtRuleKind::Media,
+            "supports" => AtRuleKind::Supports,
+            "container" => AtRuleKind::Container,
+            "layer" => AtRuleKind::Layer,
+            "scope" => AtRuleKind::Scope,
+            _ => AtRuleKind::Other,
+        };
+        Self {
+            kind,
+            name,
+            params: params.trim().to_string(),
+        }
+    }
+
+    /// Whether the enclosed rules only apply when the condition holds.
+    /// Layers always apply and only affect cascade order.
+    pub fn is_conditional(&self) -> bool {
+        matches!(
+            self.kind,
+            AtRuleKind::Media | AtRuleKind::Supports | AtRuleKind::Container | AtRuleKind::Scope
+        )
+    }
+
+    /// Short human-readable label, e.g. "dark mode" for
+    /// `@media (prefers-color-scheme: dark)`.
+    pub fn label(&self) -> String {
+        if self.kind == AtRuleKind::Media {
+            let params: String = self
+                .params
+                .to_lowercase()
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect();
+            let known = [
+                ("(prefers-color-scheme:dark)", "dark mode"),
+                ("(prefers-color-scheme:light)", "light mode"),
+                ("(prefers-reduced-motion:reduce)", "reduced motion"),
+                ("(prefers-contrast:more)", "high contrast"),
+                ("print", "print"),
+            ];
+            for (query, label) in known {
+                if params == query {
+                    return label.to_string();
+                }
+            }
+        }
+        self.to_string()
+    }
+}
+
+impl std::fmt::Display for AtRuleCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.params.is_empty() {
+            write!(f, "@{}", self.name)
+        } else {
+            write!(f, "@{} {}", self.name, self.params)
+        }
+    }
+}
+
+/// Label for the conditional at-rules in a chain (e.g., "dark mode"), or
+/// `None` when the chain contains no conditions.
+pub fn format_conditions(conditions: &[AtRuleCondition]) -> Option<String> {
+    let labels: Vec<String> = conditions
+        .iter()
+        .filter(|c| c.is_conditional())
+        .map(|c| c.label())
+        .collect();
+    if labels.is_empty() {
+        None
+    } else {
+        Some(labels.join(" and "))
+    }
+}
+
 /// Information about a DOM node
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DOMNodeInfo {
@@ -92,6 +281,30 @@ pub struct Config {
 
     /// Only show colors on variables (not inline values)
     pub color_only_on_variables: bool,
+
+    /// Stylesheet that new variables can be created in, relative to the workspace root
+    pub tokens_file: Option<String>,
+
+    /// Hint when a literal value equals an existing variable's value
+    pub token_suggestions: bool,
+
+    /// Report definitions that no `var()` in the workspace references
+    pub unused_variables: bool,
+
+    /// Glob patterns of variable names exempt from unused diagnostics (e.g., "--brand-*")
+    pub unused_allowlist: Vec<String>,
+
+    /// Longest resolved value shown in an inlay hint before truncating (0 = no limit)
+    pub inlay_hint_max_length: usize,
+
+    /// Skip inlay hints for values that are colors (they already get swatches)
+    pub inlay_hints_non_color_only: bool,
+
+    /// Whether renaming onto an existing variable name warns or is refused
+    pub rename_collision: RenameCollision,
+
+    /// Severity overrides keyed by diagnostic code (e.g., "undefined-variable")
+    pub diagnostic_severity: HashMap<String, DiagnosticLevel>,
 }
 
 impl Default for Config {
@@ -116,6 +329,14 @@ impl Default for Config {
             ],
             enable_color_provider: true,
             color_only_on_variables: false,
+            tokens_file: None,
+            token_suggestions: true,
+            unused_variables: false,
+            unused_allowlist: Vec::new(),
+            inlay_hint_max_length: 30,
+            inlay_hints_non_color_only: false,
+            rename_collision: RenameCollision::Warn,
+            diagnostic_severity: HashMap::new(),
         }
     }
 }
@@ -135,8 +356,31 @@ impl Config {
         }
         config.enable_color_provider = runtime.enable_color_provider;
         config.color_only_on_variables = runtime.color_only_on_variables;
+        config.tokens_file = runtime.tokens_file.clone();
+        config.token_suggestions = runtime.token_suggestions;
+        config.unused_variables = runtime.unused_variables;
+        if let Some(allowlist) = &runtime.unused_allowlist {
+            config.unused_allowlist = allowlist.clone();
+        }
+        config.inlay_hint_max_length = runtime.inlay_hint_max_length;
+        config.inlay_hints_non_color_only = runtime.inlay_hints_non_color_only;
+        config.rename_collision = runtime.rename_collision;
+        config.diagnostic_severity = runtime.diagnostic_severity.clone();
         config
     }
+
+    /// Severity of the diagnostic rule `code`: the configured override or
+    /// `default`, and `None` when the rule is turned off.
+    pub fn diagnostic_severity(
+        &self,
+        code: &str,
+        default: DiagnosticSeverity,
+    ) -> Option<DiagnosticSeverity> {
+        match self.diagnostic_severity.get(code) {
+            Some(level) => level.severity(),
+            None => Some(default),
+        }
+    }
 }
 
 /// Helper to convert byte offset to LSP Position
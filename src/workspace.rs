@@ -6,6 +6,7 @@ use walkdir::WalkDir;
 
 use crate::manager::CssVariableManager;
 use crate::parsers::{parse_css_document, parse_html_document};
+use crate::types::Config;
 
 /// Scan workspace folders for CSS and HTML files
 pub async fn scan_workspace(
@@ -14,7 +15,65 @@ pub async fn scan_workspace(
     mut on_progress: impl FnMut(usize, usize),
 ) -> Result<(), String> {
     let config = manager.get_config().await;
+    let folder_paths: Vec<PathBuf> = folders
+        .iter()
+        .map(|folder_uri| PathBuf::from(folder_uri.path()))
+        .collect();
+    let all_files = collect_workspace_files(&folder_paths, &config)?;
 
+    let total = all_files.len();
+
+    // Parse each file
+    for (i, file_path) in all_files.iter().enumerate() {
+        // Report progress
+        on_progress(i + 1, total);
+
+        // Read file content
+        let content = match fs::read_to_string(file_path) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+
+        // Convert to URI
+        let file_uri = match Url::from_file_path(file_path) {
+            Ok(u) => u,
+            Err(_) => continue,
+        };
+
+        // Determine file type and parse
+        let path_str = file_path.to_string_lossy();
+        let result = if path_str.ends_with(".html")
+            || path_str.ends_with(".vue")
+            || path_str.ends_with(".svelte")
+            || path_str.ends_with(".astro")
+            || path_str.ends_with(".ripple")
+        {
+            parse_html_document(&content, &file_uri, manager).await
+        } else if path_str.ends_with(".css")
+            || path_str.ends_with(".scss")
+            || path_str.ends_with(".sass")
+            || path_str.ends_with(".less")
+        {
+            parse_css_document(&content, &file_uri, manager).await
+        } else {
+            continue;
+        };
+
+        // Log errors but continue
+        if let Err(_e) = result {
+            // Silent error - could log if needed
+        }
+    }
+
+    Ok(())
+}
+
+/// List the files in workspace folders that match the lookup globs and not
+/// the ignore globs
+pub fn collect_workspace_files(
+    folders: &[PathBuf],
+    config: &Config,
+) -> Result<Vec<PathBuf>, String> {
     // Build glob matchers for lookup patterns
     let mut lookup_builder = GlobSetBuilder::new();
     for pattern in &config.lookup_files {
@@ -40,10 +99,8 @@ pub async fn scan_workspace(
     // Collect all files from all folders
     let mut all_files = Vec::new();
 
-    for folder_uri in folders {
-        let folder_path = PathBuf::from(folder_uri.path());
-
-        for entry in WalkDir::new(&folder_path)
+    for folder_path in folders {
+        for entry in WalkDir::new(folder_path)
             .follow_links(false)
             .into_iter()
             .filter_map(|e| e.ok())
@@ -56,7 +113,7 @@ pub async fn scan_workspace(
             }
 
             // Get relative path for glob matching
-            let relative = match path.strip_prefix(&folder_path) {
+            let relative = match path.strip_prefix(folder_path) {
                 Ok(rel) => rel,
                 Err(_) => continue,
             };
@@ -76,49 +133,5 @@ pub async fn scan_workspace(
         }
     }
 
-    let total = all_files.len();
-
-    // Parse each file
-    for (i, file_path) in all_files.iter().enumerate() {
-        // Report progress
-        on_progress(i + 1, total);
-
-        // Read file content
-        let content = match fs::read_to_string(file_path) {
-            Ok(c) => c,
-            Err(_) => continue,
-        };
-
-        // Convert to URI
-        let file_uri = match Url::from_file_path(file_path) {
-            Ok(u) => u,
-            Err(_) => continue,
-        };
-
-        // Determine file type and parse
-        let path_str = file_path.to_string_lossy();
-        let result = if path_str.ends_with(".html")
-            || path_str.ends_with(".vue")
-            || path_str.ends_with(".svelte")
-            || path_str.ends_with(".astro")
-            || path_str.ends_with(".ripple")
-        {
-            parse_html_document(&content, &file_uri, manager).await
-        } else if path_str.ends_with(".css")
-            || path_str.ends_with(".scss")
-            || path_str.ends_with(".sass")
-            || path_str.ends_with(".less")
-        {
-            parse_css_document(&content, &file_uri, manager).await
-        } else {
-            continue;
-        };
-
-        // Log errors but continue
-        if let Err(_e) = result {
-            // Silent error - could log if needed
-        }
-    }
-
-    Ok(())
+    Ok(all_files)
 }
@@ -1,6 +1,7 @@
 use css_variable_lsp::manager::CssVariableManager;
 use css_variable_lsp::parsers::{parse_css_document, parse_html_document};
-use css_variable_lsp::types::Config;
+use css_variable_lsp::specificity::{filter_by_dom_match, sort_by_cascade};
+use css_variable_lsp::types::{position_to_offset, Config};
 use tower_lsp::lsp_types::Url;
 
 /// Integration test: Full CSS variable workflow
@@ -637,3 +638,142 @@ async fn test_lsp_rename_preserves_fallbacks() {
         assert!(name_range.end.character > name_range.start.character);
     }
 }
+
+/// Integration test: Per-document usages and cascade winners (inlay hints)
+#[tokio::test]
+async fn test_document_usages_and_winning_definition() {
+    let manager = CssVariableManager::new(Config::default());
+    let tokens_uri = Url::parse("file:///tokens.css").unwrap();
+    let app_uri = Url::parse("file:///app.css").unwrap();
+
+    let tokens = r#"
+        :root { --space: 0.25rem; }
+        .dense { --space: 0.125rem; }
+    "#;
+    let app = r#"
+        .card { padding: var(--space); margin: var(--gap, var(--space)); }
+    "#;
+    parse_css_document(tokens, &tokens_uri, &manager)
+        .await
+        .unwrap();
+    parse_css_document(app, &app_uri, &manager).await.unwrap();
+
+    let usages = manager.get_document_usages(&app_uri).await;
+    // The var() in --gap's fallback is not a separate usage.
+    assert_eq!(usages.len(), 2);
+    assert!(usages.iter().all(|u| u.uri == app_uri));
+    assert!(manager.get_document_usages(&tokens_uri).await.is_empty());
+
+    let winner = manager.get_winning_definition("--space").await.unwrap();
+    assert_eq!(winner.selector, ".dense");
+    assert_eq!(winner.uri, tokens_uri);
+    assert!(manager.get_winning_definition("--gap").await.is_none());
+}
+
+/// Integration test: Name ranges used for document highlights in HTML
+#[tokio::test]
+async fn test_html_name_ranges_for_highlights() {
+    let manager = CssVariableManager::new(Config::default());
+    let uri = Url::parse("file:///page.html").unwrap();
+    let html = r#"<style>
+  :root { --spacing: 4px; }
+  .box { margin: var(--spacing); }
+</style>
+<div style="--spacing: 8px; padding: var(--spacing)"></div>"#;
+
+    parse_html_document(html, &uri, &manager).await.unwrap();
+
+    let (definitions, usages) = manager.get_references("--spacing").await;
+    assert_eq!(definitions.len(), 2);
+    assert_eq!(usages.len(), 2);
+
+    let name_ranges = definitions
+        .iter()
+        .map(|d| d.name_range.unwrap())
+        .chain(usages.iter().map(|u| u.name_range.unwrap()));
+    for range in name_ranges {
+        let start = position_to_offset(html, range.start).unwrap();
+        let end = position_to_offset(html, range.end).unwrap();
+        assert_eq!(&html[start..end], "--spacing");
+    }
+}
+
+/// Integration test: Definitions filtered to those matching an HTML usage's element
+#[tokio::test]
+async fn test_definitions_filtered_by_dom_match() {
+    let manager = CssVariableManager::new(Config::default());
+    let uri = Url::parse("file:///page.html").unwrap();
+    let html = r#"<style>
+  :root { --gap: 1px; }
+  .card { --gap: 2px; }
+  .other { --gap: 3px; }
+</style>
+<div class="card"><p style="margin: var(--gap)"></p></div>
+<span style="--gap: 4px"></span>"#;
+
+    parse_html_document(html, &uri, &manager).await.unwrap();
+
+    let usages = manager.get_usages("--gap").await;
+    assert_eq!(usages.len(), 1);
+    assert!(usages[0].dom_node.is_some());
+
+    let dom_tree = manager.get_dom_tree(&uri).await;
+    let definitions = manager.get_variables("--gap").await;
+    assert_eq!(definitions.len(), 4);
+
+    let mut matching = filter_by_dom_match(definitions, &usages[0], dom_tree.as_ref());
+    sort_by_cascade(&mut matching);
+    let values: Vec<&str> = matching.iter().map(|d| d.value.as_str()).collect();
+    // `.card` reaches the <p> through inheritance; `.other` and the inline
+    // definition on the <span> do not apply.
+    assert_eq!(values, vec!["2px", "1px"]);
+}
+
+/// Integration test: Usage records that undefined-variable diagnostics are built from
+#[tokio::test]
+async fn test_document_usages_skip_comments_and_strings() {
+    let manager = CssVariableManager::new(Config::default());
+    let uri = Url::parse("file:///usages.css").unwrap();
+    let css = r#"/* color: var(--commented); */
+.a { content: "var(--quoted)"; }
+.b { width: var(--width, calc(100% - (2 * 8px))); color: var(--text); }"#;
+
+    parse_css_document(css, &uri, &manager).await.unwrap();
+
+    let usages = manager.get_document_usages(&uri).await;
+    let names: Vec<&str> = usages.iter().map(|u| u.name.as_str()).collect();
+    assert_eq!(names, vec!["--width", "--text"]);
+
+    // The call range spans fallbacks with nested parentheses.
+    let start = position_to_offset(css, usages[0].range.start).unwrap();
+    let end = position_to_offset(css, usages[0].range.end).unwrap();
+    assert_eq!(&css[start..end], "var(--width, calc(100% - (2 * 8px)))");
+}
+
+/// Integration test: Undefined variables resolve through their usage's fallback
+#[tokio::test]
+async fn test_resolve_usage_through_fallback() {
+    let manager = CssVariableManager::new(Config::default());
+    let uri = Url::parse("file:///fallbacks.css").unwrap();
+    let css = r#":root { --brand: #3b82f6; }
+.a { color: var(--missing, var(--brand)); }
+.b { color: var(--brand, red); }
+.c { color: var(--missing, var(--also-missing)); }"#;
+
+    parse_css_document(css, &uri, &manager).await.unwrap();
+
+    let usages = manager.get_document_usages(&uri).await;
+    assert_eq!(usages.len(), 3);
+
+    let through_fallback = manager.resolve_usage(&usages[0]).await.unwrap();
+    assert_eq!(through_fallback.value, "#3b82f6");
+    assert_eq!(through_fallback.chain, vec!["--missing", "--brand"]);
+    assert!(manager.resolve_usage_color(&usages[0]).await.is_some());
+
+    let defined = manager.resolve_usage(&usages[1]).await.unwrap();
+    assert_eq!(defined.value, "#3b82f6");
+
+    let dead_end = manager.resolve_usage(&usages[2]).await.unwrap();
+    assert!(!dead_end.complete);
+    assert!(manager.resolve_usage_color(&usages[2]).await.is_none());
+}